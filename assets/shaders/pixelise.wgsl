@@ -6,6 +6,12 @@ var texture: texture_2d<f32>;
 @group(1) @binding(1)
 var our_sampler: sampler;
 
+@group(1) @binding(2)
+var light_map: texture_2d<f32>;
+
+@group(1) @binding(3)
+var light_map_sampler: sampler;
+
 let rows: f32 = 256.0;
 
 fn get_uv(pos: vec2<f32>) -> vec2<f32> {
@@ -20,6 +26,10 @@ fn get_texture_color(uv: vec2<f32>) -> vec4<f32> {
     return textureSample(texture, our_sampler, uv);
 }
 
+fn get_light(uv: vec2<f32>) -> vec4<f32> {
+    return textureSample(light_map, light_map_sampler, uv);
+}
+
 @fragment
 fn fragment(
     @builtin(position) position: vec4<f32>,
@@ -34,8 +44,9 @@ fn fragment(
     let texture_uv = pixelate(texture_uv, vec2(cols, rows));
 
     let color = get_texture_color(texture_uv);
+    let light = get_light(texture_uv);
 
-    return color;
+    return vec4<f32>(color.rgb * light.rgb, color.a);
 
     // return vec4<f32>(1.0, 0.0, 0.0, 1.0);
 }
\ No newline at end of file
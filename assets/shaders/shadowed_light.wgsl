@@ -0,0 +1,49 @@
+struct ShadowedLight {
+    color: vec4<f32>,
+    position: vec2<f32>,
+    radius: f32,
+    edge_count: u32,
+    edges: array<vec4<f32>, 64>,
+};
+
+@group(1) @binding(0)
+var<uniform> light: ShadowedLight;
+
+fn cross_2d(a: vec2<f32>, b: vec2<f32>) -> f32 {
+    return a.x * b.y - a.y * b.x;
+}
+
+// Whether the ray from the light to the given point crosses the edge
+fn is_blocked(point: vec2<f32>, edge: vec4<f32>) -> bool {
+    let ray = point - light.position;
+    let side = edge.zw - edge.xy;
+    let denominator = cross_2d(ray, side);
+
+    if (abs(denominator) < 0.0001) {
+        return false;
+    }
+
+    let to_edge = edge.xy - light.position;
+    let along_ray = cross_2d(to_edge, side) / denominator;
+    let along_edge = cross_2d(to_edge, ray) / denominator;
+
+    return along_ray >= 0.0 && along_ray <= 1.0 && along_edge >= 0.0 && along_edge <= 1.0;
+}
+
+@fragment
+fn fragment(
+    #import bevy_sprite::mesh2d_vertex_output
+) -> @location(0) vec4<f32> {
+    let point = world_position.xy;
+
+    for (var i: u32 = 0u; i < light.edge_count; i = i + 1u) {
+        if (is_blocked(point, light.edges[i])) {
+            return vec4<f32>(0.0);
+        }
+    }
+
+    // Same falloff as the light textures
+    let falloff = clamp(1.0 - length(point - light.position) / light.radius, 0.0, 1.0);
+
+    return vec4<f32>(light.color.rgb, light.color.a * falloff * falloff);
+}
@@ -0,0 +1,340 @@
+use std::collections::HashSet;
+
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::{
+    loading::FontAssets,
+    score::{BeamBurstKills, Score},
+    stats::RunStats,
+    storage, GameState,
+};
+
+pub struct AchievementsPlugin;
+
+/// This plugin unlocks the achievements defined in `assets/achievements.ron` during a run
+/// and draws the gallery screen during the State `GameState::Achievements`
+impl Plugin for AchievementsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(load_achievements())
+            .insert_resource(UnlockedAchievements(
+                storage::load(UNLOCKED_ACHIEVEMENTS_KEY).unwrap_or_default(),
+            ))
+            .init_resource::<ButtonColors>()
+            .add_system_set(
+                SystemSet::on_update(GameState::Playing)
+                    .with_system(unlock_achievements)
+                    .with_system(hide_toasts),
+            )
+            .add_system_set(SystemSet::on_exit(GameState::Playing).with_system(drop_toasts))
+            .add_system_set(SystemSet::on_enter(GameState::Achievements).with_system(setup_gallery))
+            .add_system_set(
+                SystemSet::on_update(GameState::Achievements).with_system(click_back_button),
+            )
+            .add_system_set(
+                SystemSet::on_exit(GameState::Achievements).with_system(cleanup_gallery),
+            );
+    }
+}
+
+const UNLOCKED_ACHIEVEMENTS_KEY: &str = "achievements";
+
+pub const TOAST_DURATION: f32 = 3.;
+
+#[derive(Deserialize)]
+pub enum AchievementCondition {
+    /// Kill this many enemies in one run
+    Kills(u32),
+    /// Reach this score in one run
+    Score(u32),
+    SurviveSeconds(u64),
+    /// Kill this many enemies during one beam
+    BeamKills(u32),
+    /// Survive this long without getting hurt
+    NoDamageSeconds(u64),
+}
+
+#[derive(Deserialize)]
+pub struct Achievement {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub condition: AchievementCondition,
+}
+
+#[derive(Deref)]
+pub struct Achievements(pub Vec<Achievement>);
+
+/// Ids of the achievements unlocked in any run so far
+#[derive(Deref, DerefMut)]
+pub struct UnlockedAchievements(pub HashSet<String>);
+
+#[derive(Component, Deref, DerefMut)]
+struct AchievementToast(Timer);
+
+#[derive(Component)]
+struct AchievementGallery;
+
+struct ButtonColors {
+    normal: UiColor,
+    hovered: UiColor,
+}
+
+impl Default for ButtonColors {
+    fn default() -> Self {
+        ButtonColors {
+            normal: Color::rgb(0.15, 0.15, 0.15).into(),
+            hovered: Color::rgb(0.25, 0.25, 0.25).into(),
+        }
+    }
+}
+
+fn load_achievements() -> Achievements {
+    Achievements(
+        ron::from_str(include_str!("../assets/achievements.ron"))
+            .expect("Failed to parse the achievement definitions"),
+    )
+}
+
+impl AchievementCondition {
+    fn is_met(&self, stats: &RunStats, score: &Score, beam_burst_kills: &BeamBurstKills) -> bool {
+        match *self {
+            AchievementCondition::Kills(kills) => stats.enemies_killed >= kills,
+            AchievementCondition::Score(points) => score.0 >= points,
+            AchievementCondition::SurviveSeconds(seconds) => {
+                stats.time_survived.as_secs() >= seconds
+            }
+            AchievementCondition::BeamKills(kills) => beam_burst_kills.0 >= kills,
+            AchievementCondition::NoDamageSeconds(seconds) => {
+                stats.damage_taken <= 0. && stats.time_survived.as_secs() >= seconds
+            }
+        }
+    }
+}
+
+fn unlock_achievements(
+    mut commands: Commands,
+    achievements: Res<Achievements>,
+    mut unlocked_achievements: ResMut<UnlockedAchievements>,
+    stats: Res<RunStats>,
+    score: Res<Score>,
+    beam_burst_kills: Res<BeamBurstKills>,
+    fonts: Res<FontAssets>,
+) {
+    let mut unlocked_any = false;
+
+    for achievement in achievements.iter() {
+        if unlocked_achievements.contains(&achievement.id)
+            || !achievement
+                .condition
+                .is_met(&stats, &score, &beam_burst_kills)
+        {
+            continue;
+        }
+
+        unlocked_achievements.insert(achievement.id.clone());
+        unlocked_any = true;
+
+        info!("achievement unlocked {}", achievement.id);
+
+        spawn_toast(&mut commands, &fonts, achievement);
+    }
+
+    if unlocked_any {
+        storage::save(UNLOCKED_ACHIEVEMENTS_KEY, &unlocked_achievements.0);
+    }
+}
+
+fn spawn_toast(commands: &mut Commands, fonts: &FontAssets, achievement: &Achievement) {
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    top: Val::Px(20.),
+                    left: Val::Percent(50.),
+                    ..default()
+                },
+                margin: UiRect {
+                    left: Val::Px(-150.),
+                    ..default()
+                },
+                size: Size::new(Val::Px(300.), Val::Auto),
+                flex_direction: FlexDirection::ColumnReverse,
+                align_items: AlignItems::Center,
+                padding: UiRect::all(Val::Px(10.)),
+                ..default()
+            },
+            color: Color::rgba(0., 0., 0., 0.8).into(),
+            ..default()
+        })
+        .with_children(|parent| {
+            parent.spawn_bundle(TextBundle::from_section(
+                format!("Achievement unlocked: {}", achievement.name),
+                TextStyle {
+                    font: fonts.fira_sans.clone(),
+                    font_size: 20.,
+                    color: Color::GOLD,
+                },
+            ));
+
+            parent.spawn_bundle(TextBundle::from_section(
+                achievement.description.clone(),
+                TextStyle {
+                    font: fonts.fira_sans.clone(),
+                    font_size: 16.,
+                    color: Color::WHITE,
+                },
+            ));
+        })
+        .insert(Name::new("AchievementToast"))
+        .insert(AchievementToast(Timer::from_seconds(TOAST_DURATION, false)));
+}
+
+fn hide_toasts(
+    mut commands: Commands,
+    mut toasts: Query<(Entity, &mut AchievementToast)>,
+    time: Res<Time>,
+) {
+    for (entity, mut toast) in toasts.iter_mut() {
+        if toast.tick(time.delta()).finished() {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+fn drop_toasts(mut commands: Commands, toasts: Query<Entity, With<AchievementToast>>) {
+    for toast in toasts.iter() {
+        commands.entity(toast).despawn_recursive();
+    }
+}
+
+fn setup_gallery(
+    mut commands: Commands,
+    font_assets: Res<FontAssets>,
+    button_colors: Res<ButtonColors>,
+    achievements: Res<Achievements>,
+    unlocked_achievements: Res<UnlockedAchievements>,
+) {
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                flex_direction: FlexDirection::ColumnReverse,
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            color: Color::NONE.into(),
+            ..default()
+        })
+        .with_children(|parent| {
+            parent.spawn_bundle(
+                TextBundle::from_section(
+                    format!(
+                        "Achievements {}/{}",
+                        unlocked_achievements.len(),
+                        achievements.len()
+                    ),
+                    TextStyle {
+                        font: font_assets.fira_sans.clone(),
+                        font_size: 48.0,
+                        color: Color::WHITE,
+                    },
+                )
+                .with_style(Style {
+                    margin: UiRect {
+                        bottom: Val::Percent(2.),
+                        ..default()
+                    },
+                    ..default()
+                }),
+            );
+
+            for achievement in achievements.iter() {
+                let unlocked = unlocked_achievements.contains(&achievement.id);
+
+                parent.spawn_bundle(TextBundle::from_sections([
+                    TextSection::new(
+                        format!("{}  ", achievement.name),
+                        TextStyle {
+                            font: font_assets.fira_sans.clone(),
+                            font_size: 24.0,
+                            color: if unlocked {
+                                Color::GOLD
+                            } else {
+                                Color::rgb(0.4, 0.4, 0.4)
+                            },
+                        },
+                    ),
+                    TextSection::new(
+                        achievement.description.clone(),
+                        TextStyle {
+                            font: font_assets.fira_sans.clone(),
+                            font_size: 18.0,
+                            color: if unlocked {
+                                Color::WHITE
+                            } else {
+                                Color::rgb(0.4, 0.4, 0.4)
+                            },
+                        },
+                    ),
+                ]));
+            }
+
+            // Back button
+            parent
+                .spawn_bundle(ButtonBundle {
+                    style: Style {
+                        size: Size::new(Val::Px(120.0), Val::Px(50.0)),
+                        margin: UiRect {
+                            top: Val::Percent(3.),
+                            ..default()
+                        },
+                        justify_content: JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        ..Default::default()
+                    },
+                    color: button_colors.normal,
+                    ..Default::default()
+                })
+                .with_children(|parent| {
+                    parent.spawn_bundle(TextBundle::from_section(
+                        "Back",
+                        TextStyle {
+                            font: font_assets.fira_sans.clone(),
+                            font_size: 40.0,
+                            color: Color::rgb(0.9, 0.9, 0.9),
+                        },
+                    ));
+                });
+        })
+        .insert(AchievementGallery);
+}
+
+fn click_back_button(
+    button_colors: Res<ButtonColors>,
+    mut state: ResMut<State<GameState>>,
+    mut interaction_query: Query<
+        (&Interaction, &mut UiColor),
+        (Changed<Interaction>, With<Button>),
+    >,
+) {
+    for (interaction, mut color) in &mut interaction_query {
+        match *interaction {
+            Interaction::Clicked => {
+                state.set(GameState::Menu).unwrap();
+            }
+            Interaction::Hovered => {
+                *color = button_colors.hovered;
+            }
+            Interaction::None => {
+                *color = button_colors.normal;
+            }
+        }
+    }
+}
+
+fn cleanup_gallery(mut commands: Commands, gallery: Query<Entity, With<AchievementGallery>>) {
+    commands.entity(gallery.single()).despawn_recursive();
+}
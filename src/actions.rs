@@ -1,77 +1,368 @@
+use crate::camera::{screen_size, MainCamera};
 use crate::GameState;
+use bevy::input::mouse::MouseWheel;
+use bevy::math::Vec3Swizzles;
 use bevy::prelude::*;
 
 pub struct ActionsPlugin;
 
-// This plugin listens for keyboard input and converts the input into Actions
-// Actions can then be used as a resource in other systems to act on the player input.
+// This plugin listens for keyboard, mouse and gamepad input and converts the input into Actions
+// Actions can then be used as a resource in other systems to act on the input,
+// while the PlayerActions component holds the input of each player.
 impl Plugin for ActionsPlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<Actions>().add_system_set(
-            SystemSet::on_update(GameState::Playing).with_system(set_movement_actions),
+            SystemSet::on_update(GameState::Playing)
+                .label("player_actions")
+                .with_system(set_movement_actions)
+                .with_system(set_aim_actions)
+                .with_system(set_weapon_actions)
+                .with_system(set_dash_actions)
+                .with_system(set_repair_actions)
+                .with_system(set_mirror_actions)
+                .with_system(set_ui_actions),
         );
     }
 }
 
+/// Stick input below this is ignored
+pub const GAMEPAD_DEADZONE: f32 = 0.2;
+
+/// Actions that are not tied to one player
 #[derive(Default)]
 pub struct Actions {
-    pub player_movement: Option<Vec2>,
-}
-
-fn set_movement_actions(mut actions: ResMut<Actions>, keyboard_input: Res<Input<KeyCode>>) {
-    if GameControl::Up.just_released(&keyboard_input)
-        || GameControl::Up.pressed(&keyboard_input)
-        || GameControl::Left.just_released(&keyboard_input)
-        || GameControl::Left.pressed(&keyboard_input)
-        || GameControl::Down.just_released(&keyboard_input)
-        || GameControl::Down.pressed(&keyboard_input)
-        || GameControl::Right.just_released(&keyboard_input)
-        || GameControl::Right.pressed(&keyboard_input)
+    pub toggle_minimap: bool,
+}
+
+/// The input of one player, read from its [InputDevice]
+#[derive(Component, Default)]
+pub struct PlayerActions {
+    pub movement: Option<Vec2>,
+    /// The direction the player wants to face
+    pub aim: Option<Vec2>,
+    /// The aim comes from a stick or a touch, which aim assist helps with
+    pub aim_assisted: bool,
+    /// Taken by the weapon once it has switched
+    pub switch_weapon: Option<WeaponSwitch>,
+    /// Taken by the player once it has dashed
+    pub dash: bool,
+    /// Held to repair the barricade the player is standing next to
+    pub repair: bool,
+    /// Taken by the player once it has put down or picked up a mirror
+    pub mirror: bool,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum WeaponSwitch {
+    /// The weapon with this index in [crate::weapon::WeaponDefinitions]
+    Slot(usize),
+    Next,
+    Previous,
+}
+
+/// Where the [PlayerActions] of a player come from
+#[derive(Component, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum InputDevice {
+    KeyboardMouse,
+    /// The n-th connected gamepad
+    Gamepad(usize),
+    /// Controlled from another machine, see [crate::network]
+    Network,
+}
+
+fn set_ui_actions(mut actions: ResMut<Actions>, keyboard_input: Res<Input<KeyCode>>) {
+    actions.toggle_minimap = keyboard_input.just_pressed(KeyCode::M);
+}
+
+fn set_movement_actions(
+    mut players: Query<(&InputDevice, &mut PlayerActions)>,
+    keyboard_input: Res<Input<KeyCode>>,
+    gamepads: Res<Gamepads>,
+    gamepad_axes: Res<Axis<GamepadAxis>>,
+) {
+    for (input_device, mut actions) in players.iter_mut() {
+        actions.movement = match *input_device {
+            InputDevice::KeyboardMouse => keyboard_movement(actions.movement, &keyboard_input),
+            InputDevice::Gamepad(index) => gamepad_stick(
+                &gamepads,
+                &gamepad_axes,
+                index,
+                GamepadAxisType::LeftStickX,
+                GamepadAxisType::LeftStickY,
+            ),
+            InputDevice::Network => actions.movement,
+        };
+    }
+}
+
+fn set_aim_actions(
+    mut players: Query<(&InputDevice, &Transform, &mut PlayerActions)>,
+    windows: Res<Windows>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    gamepads: Res<Gamepads>,
+    gamepad_axes: Res<Axis<GamepadAxis>>,
+    touches: Res<Touches>,
+) {
+    let (camera, camera_transform) = camera_query.single();
+    let window_size = screen_size(&windows);
+
+    let to_world = |screen_position: Vec2| {
+        let ndc = (screen_position / window_size) * 2.0 - Vec2::ONE;
+        let ndc_to_world = camera_transform.compute_matrix() * camera.projection_matrix().inverse();
+
+        ndc_to_world.project_point3(ndc.extend(-1.0)).truncate()
+    };
+
+    // get world cursor position
+    let cursor_world_position = windows
+        .get_primary()
+        .and_then(|window| window.cursor_position())
+        .map(to_world);
+
+    // Touch positions start at the top of the window unlike the cursor
+    let touch_world_position = touches.iter().last().map(|touch| {
+        to_world(Vec2::new(
+            touch.position().x,
+            window_size.y - touch.position().y,
+        ))
+    });
+
+    for (input_device, transform, mut actions) in players.iter_mut() {
+        actions.aim_assisted = match *input_device {
+            InputDevice::KeyboardMouse => touch_world_position.is_some(),
+            InputDevice::Gamepad(_) => true,
+            InputDevice::Network => false,
+        };
+
+        actions.aim = match *input_device {
+            InputDevice::KeyboardMouse => touch_world_position
+                .or(cursor_world_position)
+                .map(|world_position| world_position - transform.translation.xy())
+                .and_then(|to_target| to_target.try_normalize()),
+            InputDevice::Gamepad(index) => gamepad_stick(
+                &gamepads,
+                &gamepad_axes,
+                index,
+                GamepadAxisType::RightStickX,
+                GamepadAxisType::RightStickY,
+            )
+            .and_then(|stick| stick.try_normalize()),
+            InputDevice::Network => actions.aim,
+        };
+    }
+}
+
+fn set_weapon_actions(
+    mut players: Query<(&InputDevice, &mut PlayerActions)>,
+    keyboard_input: Res<Input<KeyCode>>,
+    mut mouse_wheel_events: EventReader<MouseWheel>,
+    gamepads: Res<Gamepads>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+) {
+    let scroll = mouse_wheel_events.iter().map(|event| event.y).sum::<f32>();
+
+    let number_keys = [
+        KeyCode::Key1,
+        KeyCode::Key2,
+        KeyCode::Key3,
+        KeyCode::Key4,
+        KeyCode::Key5,
+        KeyCode::Key6,
+        KeyCode::Key7,
+        KeyCode::Key8,
+        KeyCode::Key9,
+    ];
+
+    for (input_device, mut actions) in players.iter_mut() {
+        let switch = match *input_device {
+            InputDevice::KeyboardMouse => number_keys
+                .iter()
+                .position(|key| keyboard_input.just_pressed(*key))
+                .map(WeaponSwitch::Slot)
+                .or(if scroll < 0. {
+                    Some(WeaponSwitch::Next)
+                } else if scroll > 0. {
+                    Some(WeaponSwitch::Previous)
+                } else {
+                    None
+                }),
+            InputDevice::Gamepad(index) => {
+                gamepads.iter().nth(index).cloned().and_then(|gamepad| {
+                    let just_pressed = |button_type| {
+                        gamepad_buttons.just_pressed(GamepadButton::new(gamepad, button_type))
+                    };
+
+                    if just_pressed(GamepadButtonType::RightTrigger) {
+                        Some(WeaponSwitch::Next)
+                    } else if just_pressed(GamepadButtonType::LeftTrigger) {
+                        Some(WeaponSwitch::Previous)
+                    } else {
+                        None
+                    }
+                })
+            }
+            // Set when the input arrives
+            InputDevice::Network => continue,
+        };
+
+        if switch.is_some() {
+            actions.switch_weapon = switch;
+        }
+    }
+}
+
+fn set_dash_actions(
+    mut players: Query<(&InputDevice, &mut PlayerActions)>,
+    keyboard_input: Res<Input<KeyCode>>,
+    gamepads: Res<Gamepads>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+) {
+    for (input_device, mut actions) in players.iter_mut() {
+        let dash = match *input_device {
+            InputDevice::KeyboardMouse => {
+                keyboard_input.just_pressed(KeyCode::Space)
+                    || keyboard_input.just_pressed(KeyCode::LShift)
+            }
+            InputDevice::Gamepad(index) => match gamepads.iter().nth(index) {
+                Some(gamepad) => gamepad_buttons
+                    .just_pressed(GamepadButton::new(*gamepad, GamepadButtonType::South)),
+                None => false,
+            },
+            // Set when the input arrives
+            InputDevice::Network => continue,
+        };
+
+        if dash {
+            actions.dash = true;
+        }
+    }
+}
+
+fn set_repair_actions(
+    mut players: Query<(&InputDevice, &mut PlayerActions)>,
+    keyboard_input: Res<Input<KeyCode>>,
+    gamepads: Res<Gamepads>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+) {
+    for (input_device, mut actions) in players.iter_mut() {
+        actions.repair = match *input_device {
+            InputDevice::KeyboardMouse => keyboard_input.pressed(KeyCode::E),
+            InputDevice::Gamepad(index) => match gamepads.iter().nth(index) {
+                Some(gamepad) => {
+                    gamepad_buttons.pressed(GamepadButton::new(*gamepad, GamepadButtonType::West))
+                }
+                None => false,
+            },
+            // Set when the input arrives
+            InputDevice::Network => continue,
+        };
+    }
+}
+
+fn set_mirror_actions(
+    mut players: Query<(&InputDevice, &mut PlayerActions)>,
+    keyboard_input: Res<Input<KeyCode>>,
+    gamepads: Res<Gamepads>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+) {
+    for (input_device, mut actions) in players.iter_mut() {
+        let mirror = match *input_device {
+            InputDevice::KeyboardMouse => keyboard_input.just_pressed(KeyCode::Q),
+            InputDevice::Gamepad(index) => match gamepads.iter().nth(index) {
+                Some(gamepad) => gamepad_buttons
+                    .just_pressed(GamepadButton::new(*gamepad, GamepadButtonType::North)),
+                None => false,
+            },
+            // Set when the input arrives
+            InputDevice::Network => continue,
+        };
+
+        if mirror {
+            actions.mirror = true;
+        }
+    }
+}
+
+/// Position of a stick of the n-th connected gamepad, `None` inside of the deadzone
+fn gamepad_stick(
+    gamepads: &Gamepads,
+    gamepad_axes: &Axis<GamepadAxis>,
+    index: usize,
+    x_axis: GamepadAxisType,
+    y_axis: GamepadAxisType,
+) -> Option<Vec2> {
+    let gamepad = gamepads.iter().nth(index).cloned()?;
+
+    let stick = Vec2::new(
+        gamepad_axes.get(GamepadAxis::new(gamepad, x_axis))?,
+        gamepad_axes.get(GamepadAxis::new(gamepad, y_axis))?,
+    );
+
+    if stick.length() < GAMEPAD_DEADZONE {
+        None
+    } else {
+        Some(stick.clamp_length_max(1.))
+    }
+}
+
+fn keyboard_movement(
+    previous_movement: Option<Vec2>,
+    keyboard_input: &Res<Input<KeyCode>>,
+) -> Option<Vec2> {
+    if GameControl::Up.just_released(keyboard_input)
+        || GameControl::Up.pressed(keyboard_input)
+        || GameControl::Left.just_released(keyboard_input)
+        || GameControl::Left.pressed(keyboard_input)
+        || GameControl::Down.just_released(keyboard_input)
+        || GameControl::Down.pressed(keyboard_input)
+        || GameControl::Right.just_released(keyboard_input)
+        || GameControl::Right.pressed(keyboard_input)
     {
         let mut player_movement = Vec2::ZERO;
 
-        if GameControl::Up.just_released(&keyboard_input)
-            || GameControl::Down.just_released(&keyboard_input)
+        if GameControl::Up.just_released(keyboard_input)
+            || GameControl::Down.just_released(keyboard_input)
         {
-            if GameControl::Up.pressed(&keyboard_input) {
+            if GameControl::Up.pressed(keyboard_input) {
                 player_movement.y = 1.;
-            } else if GameControl::Down.pressed(&keyboard_input) {
+            } else if GameControl::Down.pressed(keyboard_input) {
                 player_movement.y = -1.;
             } else {
                 player_movement.y = 0.;
             }
-        } else if GameControl::Up.just_pressed(&keyboard_input) {
+        } else if GameControl::Up.just_pressed(keyboard_input) {
             player_movement.y = 1.;
-        } else if GameControl::Down.just_pressed(&keyboard_input) {
+        } else if GameControl::Down.just_pressed(keyboard_input) {
             player_movement.y = -1.;
         } else {
-            player_movement.y = actions.player_movement.unwrap_or(Vec2::ZERO).y;
+            player_movement.y = previous_movement.unwrap_or(Vec2::ZERO).y;
         }
 
-        if GameControl::Right.just_released(&keyboard_input)
-            || GameControl::Left.just_released(&keyboard_input)
+        if GameControl::Right.just_released(keyboard_input)
+            || GameControl::Left.just_released(keyboard_input)
         {
-            if GameControl::Right.pressed(&keyboard_input) {
+            if GameControl::Right.pressed(keyboard_input) {
                 player_movement.x = 1.;
-            } else if GameControl::Left.pressed(&keyboard_input) {
+            } else if GameControl::Left.pressed(keyboard_input) {
                 player_movement.x = -1.;
             } else {
                 player_movement.x = 0.;
             }
-        } else if GameControl::Right.just_pressed(&keyboard_input) {
+        } else if GameControl::Right.just_pressed(keyboard_input) {
             player_movement.x = 1.;
-        } else if GameControl::Left.just_pressed(&keyboard_input) {
+        } else if GameControl::Left.just_pressed(keyboard_input) {
             player_movement.x = -1.;
         } else {
-            player_movement.x = actions.player_movement.unwrap_or(Vec2::ZERO).x;
+            player_movement.x = previous_movement.unwrap_or(Vec2::ZERO).x;
         }
 
         if player_movement != Vec2::ZERO {
-            player_movement = player_movement.normalize();
-            actions.player_movement = Some(player_movement);
+            Some(player_movement.normalize())
+        } else {
+            previous_movement
         }
     } else {
-        actions.player_movement = None;
+        None
     }
 }
 
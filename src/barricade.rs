@@ -0,0 +1,185 @@
+use crate::{
+    actions::PlayerActions,
+    character::Health,
+    enemy::Enemy,
+    game_area::EnemySpawn,
+    network::NetworkRole,
+    player::Player,
+    score::{ScoreCategory, ScoreEvent},
+    GameState, WALL_WIDTH,
+};
+use bevy::{math::Vec3Swizzles, prelude::*};
+
+pub struct BarricadePlugin;
+
+/// This plugin boards up the enemy spawn windows.
+/// Enemies coming in through a window first have to tear its boards off,
+/// players standing next to it can hold the repair button to nail them back on for points.
+impl Plugin for BarricadePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::on_update(GameState::Playing)
+                .with_system(board_up_windows)
+                .with_system(breach_barricades)
+                .with_system(repair_barricades)
+                .with_system(update_boards),
+        );
+    }
+}
+
+pub const BARRICADE_BOARDS: u32 = 5;
+pub const BOARD_HEALTH: f32 = 20.;
+/// Damage per second each enemy deals to the boards of the window it is coming through
+pub const BARRICADE_TEAR_RATE: f32 = 12.;
+/// Seconds it takes to nail one board back on
+pub const BOARD_REPAIR_TIME: f32 = 0.75;
+/// How close to a window the player has to be to repair it
+pub const REPAIR_RANGE: f32 = 80.;
+pub const BOARD_REPAIR_SCORE: u32 = 25;
+
+/// A spawn window with boards on it, the boards are its [Health]
+#[derive(Component)]
+pub struct Barricade {
+    repair_timer: Timer,
+}
+
+impl Barricade {
+    pub fn boards(health: &Health) -> u32 {
+        (health.get_health().max(0.) / BOARD_HEALTH).ceil() as u32
+    }
+
+    /// Puts the barricade at this many boards, like clients do with the count the host sends
+    pub fn set_boards(health: &mut Health, boards: u32) {
+        if Barricade::boards(health) != boards {
+            health.set_health(boards as f32 * BOARD_HEALTH);
+        }
+    }
+}
+
+/// One of the boards of a [Barricade], shown while the barricade has more boards than its index
+#[derive(Component)]
+struct Board(u32);
+
+/// Keeps the enemy in front of the window until it has broken through
+#[derive(Component)]
+pub struct Breaching {
+    pub window: Entity,
+}
+
+fn board_up_windows(mut commands: Commands, windows: Query<Entity, Added<EnemySpawn>>) {
+    for window in windows.iter() {
+        commands
+            .entity(window)
+            .insert(Barricade {
+                repair_timer: Timer::from_seconds(BOARD_REPAIR_TIME, true),
+            })
+            .insert(Health::new(BARRICADE_BOARDS as f32 * BOARD_HEALTH))
+            .with_children(|parent| {
+                for i in 0..BARRICADE_BOARDS {
+                    // Nailed on crooked, alternating the tilt
+                    let tilt = if i % 2 == 0 { 0.2 } else { -0.2 };
+
+                    parent
+                        .spawn_bundle(SpriteBundle {
+                            sprite: Sprite {
+                                color: Color::rgb(0.45, 0.3, 0.15),
+                                custom_size: Some(Vec2::new(WALL_WIDTH, 3.)),
+                                ..default()
+                            },
+                            transform: Transform {
+                                translation: Vec3::new(
+                                    0.,
+                                    (i as f32 - (BARRICADE_BOARDS - 1) as f32 / 2.) * 4.5,
+                                    0.1,
+                                ),
+                                rotation: Quat::from_rotation_z(tilt),
+                                ..default()
+                            },
+                            ..default()
+                        })
+                        .insert(Board(i))
+                        .insert(Name::new("Board"));
+                }
+            });
+    }
+}
+
+fn breach_barricades(
+    mut commands: Commands,
+    enemies: Query<(Entity, &Breaching), With<Enemy>>,
+    mut windows: Query<&mut Health, With<Barricade>>,
+    time: Res<Time>,
+    network_role: Res<NetworkRole>,
+) {
+    if !network_role.is_authority() {
+        return;
+    }
+
+    for (enemy, breaching) in enemies.iter() {
+        let mut health = match windows.get_mut(breaching.window) {
+            Ok(health) => health,
+            Err(_) => {
+                commands.entity(enemy).remove::<Breaching>();
+                continue;
+            }
+        };
+
+        // Broken windows let the enemies straight in
+        if Barricade::boards(&health) == 0
+            || health.take_damage(BARRICADE_TEAR_RATE * time.delta_seconds())
+        {
+            health.set_health(0.);
+            commands.entity(enemy).remove::<Breaching>();
+        }
+    }
+}
+
+fn repair_barricades(
+    mut windows: Query<(&Transform, &mut Health, &mut Barricade)>,
+    players: Query<(&Transform, &PlayerActions), With<Player>>,
+    mut score_events: EventWriter<ScoreEvent>,
+    time: Res<Time>,
+    network_role: Res<NetworkRole>,
+) {
+    if !network_role.is_authority() {
+        return;
+    }
+
+    for (transform, mut health, mut barricade) in windows.iter_mut() {
+        let position = transform.translation.xy();
+
+        let repairing = players.iter().any(|(player_transform, actions)| {
+            actions.repair && player_transform.translation.xy().distance(position) < REPAIR_RANGE
+        });
+
+        if !repairing || health.get_health() >= health.get_max_health() {
+            barricade.repair_timer.reset();
+            continue;
+        }
+
+        if !barricade.repair_timer.tick(time.delta()).just_finished() {
+            continue;
+        }
+
+        // Always put a whole board back on
+        let boards = Barricade::boards(&health) + 1;
+        Barricade::set_boards(&mut health, boards);
+
+        score_events.send(ScoreEvent {
+            category: ScoreCategory::Repair,
+            points: BOARD_REPAIR_SCORE,
+            position,
+        });
+    }
+}
+
+fn update_boards(
+    windows: Query<&Health, (With<Barricade>, Changed<Health>)>,
+    mut boards: Query<(&Board, &Parent, &mut Visibility)>,
+) {
+    for (board, parent, mut visibility) in boards.iter_mut() {
+        if let Ok(health) = windows.get(parent.get()) {
+            visibility.is_visible = board.0 < Barricade::boards(health);
+        }
+    }
+}
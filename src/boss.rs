@@ -0,0 +1,302 @@
+use std::f32::consts::TAU;
+
+use crate::{
+    character::{Health, Movement},
+    enemy::{
+        spawn_point_position, Attack, AttackKind, EnemyBundle, EnemyDamagedEvent, WeakPoint,
+        MELEE_ATTACK_RANGE, PROJECTILE_SPEED,
+    },
+    game_area::EnemySpawn,
+    loading::TextureAssets,
+    network::NetworkRole,
+    projectile::ProjectileBundle,
+    score::{Score, ScoreCategory, ScoreEvent, ScoreMultiplier},
+    GameState,
+};
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use rand::seq::IteratorRandom;
+
+pub struct BossPlugin;
+
+/// This plugin sends in a boss every [BOSS_INTERVAL] seconds or every [BOSS_SCORE_STEP] points.
+/// The boss is an enemy like any other, so it chases, attacks and takes damage the same way,
+/// on top of that it goes through its attack phases and has weak points on its sides.
+impl Plugin for BossPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::on_enter(GameState::Playing).with_system(setup_boss_schedule),
+        )
+        .add_system_set(
+            SystemSet::on_update(GameState::Playing)
+                .with_system(spawn_bosses)
+                .with_system(boss_phases)
+                // Before the schedule lets go of the killed boss
+                .with_system(score_boss_kills.before(spawn_bosses)),
+        )
+        .add_system_set(SystemSet::on_exit(GameState::Playing).with_system(boss_schedule_cleanup));
+    }
+}
+
+pub const BOSS_INTERVAL: f32 = 120.;
+pub const BOSS_SCORE_STEP: u32 = 10000;
+pub const BOSS_HEALTH: f32 = 4000.;
+pub const BOSS_KILL_BONUS: u32 = 2000;
+pub const BOSS_SPEED: f32 = 40.;
+pub const BOSS_CHARGE_SPEED: f32 = 320.;
+/// The boss barely moves when knocked back
+pub const BOSS_MASS: f32 = 6.;
+/// Seconds each of the attack phases lasts
+pub const BOSS_PHASE_TIME: f32 = 6.;
+pub const BOSS_MINIONS: usize = 3;
+/// Projectiles fired all around the boss at once during [BossPhase::AreaDenial]
+pub const BOSS_AREA_DENIAL_PROJECTILES: usize = 16;
+pub const WEAK_POINT_MULTIPLIER: f32 = 3.;
+
+#[derive(Component)]
+pub struct Boss;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BossPhase {
+    /// Winds up and rushes at the player
+    Charge,
+    /// Calls in minions through the enemy spawn windows
+    Summon,
+    /// Fires rings of projectiles to fill the room
+    AreaDenial,
+}
+
+impl BossPhase {
+    fn next(self) -> Self {
+        match self {
+            BossPhase::Charge => BossPhase::Summon,
+            BossPhase::Summon => BossPhase::AreaDenial,
+            BossPhase::AreaDenial => BossPhase::Charge,
+        }
+    }
+
+    /// Seconds between the repeated actions of the phase
+    fn action_interval(self) -> f32 {
+        match self {
+            BossPhase::Charge => 2.,
+            BossPhase::Summon => BOSS_PHASE_TIME,
+            BossPhase::AreaDenial => 1.5,
+        }
+    }
+}
+
+#[derive(Component)]
+struct BossBehaviour {
+    phase: BossPhase,
+    phase_timer: Timer,
+    action_timer: Timer,
+}
+
+impl BossBehaviour {
+    fn enter(&mut self, phase: BossPhase) {
+        self.phase = phase;
+        self.phase_timer.reset();
+        self.action_timer = Timer::from_seconds(phase.action_interval(), true);
+    }
+}
+
+/// Decides when the next boss comes
+struct BossSchedule {
+    timer: Timer,
+    next_score: u32,
+    boss: Option<Entity>,
+}
+
+pub fn spawn_boss(commands: &mut Commands, textures: &TextureAssets, position: Vec2) -> Entity {
+    commands
+        .spawn_bundle(EnemyBundle::at(position, textures))
+        .insert(Boss)
+        .insert(BossBehaviour {
+            phase: BossPhase::Charge,
+            phase_timer: Timer::from_seconds(BOSS_PHASE_TIME, false),
+            action_timer: Timer::from_seconds(BossPhase::Charge.action_interval(), true),
+        })
+        .insert(Health::new(BOSS_HEALTH))
+        .insert(
+            Movement::new(BOSS_SPEED)
+                .with_acceleration(1200., 1200.)
+                .with_mass(BOSS_MASS),
+        )
+        .insert(Attack {
+            damage: 25.,
+            range: MELEE_ATTACK_RANGE * 3.,
+            cooldown: Timer::from_seconds(1.5, false),
+            kind: AttackKind::Melee,
+        })
+        .insert(Sprite {
+            color: Color::rgb(1., 0.5, 0.5),
+            ..default()
+        })
+        .insert(Transform {
+            translation: position.extend(2.),
+            scale: Vec3::new(0.75, 0.75, 1.),
+            ..default()
+        })
+        .insert(Name::new("Boss"))
+        .with_children(|parent| {
+            // Glowing cores on both sides, the boss has to be flanked to hit them
+            for side in [-1., 1.] {
+                parent
+                    .spawn_bundle(SpriteBundle {
+                        sprite: Sprite {
+                            color: Color::YELLOW,
+                            custom_size: Some(Vec2::splat(40.)),
+                            ..default()
+                        },
+                        transform: Transform::from_xyz(side * 80., 0., 0.1),
+                        ..default()
+                    })
+                    .insert(WeakPoint {
+                        multiplier: WEAK_POINT_MULTIPLIER,
+                    })
+                    .insert(Collider::ball(20.))
+                    .insert(Name::new("Weak Point"));
+            }
+        })
+        .id()
+}
+
+fn setup_boss_schedule(mut commands: Commands) {
+    commands.insert_resource(BossSchedule {
+        timer: Timer::from_seconds(BOSS_INTERVAL, false),
+        next_score: BOSS_SCORE_STEP,
+        boss: None,
+    });
+}
+
+fn spawn_bosses(
+    mut commands: Commands,
+    mut schedule: ResMut<BossSchedule>,
+    bosses: Query<(), With<Boss>>,
+    enemy_spawn_points: Query<&Transform, With<EnemySpawn>>,
+    score: Res<Score>,
+    time: Res<Time>,
+    textures: Res<TextureAssets>,
+    network_role: Res<NetworkRole>,
+) {
+    // Clients get their bosses from the host
+    if !network_role.is_authority() {
+        return;
+    }
+
+    // One boss at a time
+    if let Some(boss) = schedule.boss {
+        if bosses.get(boss).is_ok() {
+            return;
+        }
+
+        schedule.boss = None;
+    }
+
+    let due = schedule.timer.tick(time.delta()).finished() || score.0 >= schedule.next_score;
+
+    if !due {
+        return;
+    }
+
+    let spawn_point = match enemy_spawn_points.iter().choose(&mut rand::thread_rng()) {
+        Some(spawn_point) => spawn_point,
+        None => return,
+    };
+
+    schedule.boss = Some(spawn_boss(
+        &mut commands,
+        &textures,
+        spawn_point_position(spawn_point),
+    ));
+    schedule.timer.reset();
+    schedule.next_score = score.0 + BOSS_SCORE_STEP;
+}
+
+fn boss_phases(
+    mut commands: Commands,
+    mut bosses: Query<(&Transform, &mut Movement, &mut BossBehaviour), With<Boss>>,
+    enemy_spawn_points: Query<&Transform, With<EnemySpawn>>,
+    time: Res<Time>,
+    textures: Res<TextureAssets>,
+    network_role: Res<NetworkRole>,
+) {
+    if !network_role.is_authority() {
+        return;
+    }
+
+    for (transform, mut movement, mut behaviour) in bosses.iter_mut() {
+        if behaviour.phase_timer.tick(time.delta()).finished() {
+            let next_phase = behaviour.phase.next();
+            behaviour.enter(next_phase);
+
+            if next_phase == BossPhase::Summon {
+                let spawn_points = enemy_spawn_points
+                    .iter()
+                    .choose_multiple(&mut rand::thread_rng(), BOSS_MINIONS);
+
+                for spawn_point in spawn_points {
+                    commands.spawn_bundle(EnemyBundle::at(
+                        spawn_point_position(spawn_point),
+                        &textures,
+                    ));
+                }
+            }
+        }
+
+        let acted = behaviour.action_timer.tick(time.delta()).just_finished();
+
+        match behaviour.phase {
+            BossPhase::Charge => {
+                // Stand still for the first half of every charge, then rush
+                movement.speed = if behaviour.action_timer.percent() > 0.5 {
+                    BOSS_CHARGE_SPEED
+                } else {
+                    0.
+                };
+            }
+            BossPhase::Summon => {
+                movement.speed = BOSS_SPEED;
+            }
+            BossPhase::AreaDenial => {
+                movement.speed = 0.;
+
+                if acted {
+                    let position = transform.translation.truncate();
+
+                    for i in 0..BOSS_AREA_DENIAL_PROJECTILES {
+                        let angle = i as f32 / BOSS_AREA_DENIAL_PROJECTILES as f32 * TAU;
+                        let direction = Vec2::new(angle.cos(), angle.sin());
+
+                        commands.spawn_bundle(ProjectileBundle::new(
+                            position + direction * 70.,
+                            direction * PROJECTILE_SPEED * 0.6,
+                            10.,
+                        ));
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn score_boss_kills(
+    mut enemy_damaged_events: EventReader<EnemyDamagedEvent>,
+    mut score_events: EventWriter<ScoreEvent>,
+    schedule: Res<BossSchedule>,
+    score_multiplier: Res<ScoreMultiplier>,
+) {
+    for event in enemy_damaged_events.iter() {
+        if event.killed && Some(event.enemy) == schedule.boss {
+            score_events.send(ScoreEvent {
+                category: ScoreCategory::Boss,
+                points: BOSS_KILL_BONUS * score_multiplier.0,
+                position: event.position,
+            });
+        }
+    }
+}
+
+fn boss_schedule_cleanup(mut commands: Commands) {
+    commands.remove_resource::<BossSchedule>();
+}
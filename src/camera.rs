@@ -1,4 +1,5 @@
 use bevy::{
+    core_pipeline::clear_color::ClearColorConfig,
     prelude::*,
     render::{
         camera::RenderTarget,
@@ -21,18 +22,31 @@ impl Plugin for CameraPlugin {
     }
 }
 
+/// Size of the screen on a headless server, which has no window to take it from
+pub const HEADLESS_SCREEN_SIZE: Vec2 = Vec2::new(1280., 720.);
+
 #[derive(Component)]
 pub struct MainCamera;
 
+/// Renders the lights of the scene into the light map, see [crate::lighting]
+#[derive(Component)]
+pub struct LightCamera;
+
 #[derive(Component)]
 pub struct Screen;
 
 #[derive(Deref, DerefMut)]
 pub struct PostProcessingLayer(pub RenderLayers);
 
+#[derive(Deref, DerefMut)]
+pub struct LightingLayer(pub RenderLayers);
+
 #[derive(Deref, DerefMut)]
 pub struct CameraRenderImage(pub Handle<Image>);
 
+#[derive(Deref, DerefMut)]
+pub struct LightMapImage(pub Handle<Image>);
+
 #[derive(Deref, DerefMut)]
 pub struct ScreenRes(pub Entity);
 
@@ -43,34 +57,19 @@ fn camera_setup(
     mut images: ResMut<Assets<Image>>,
     mut post_processing_materials: ResMut<Assets<PixeliseMaterial>>,
 ) {
-    let window = windows.primary();
+    let window_size = screen_size(&windows);
 
     let size = Extent3d {
-        width: window.width() as u32,
-        height: window.height() as u32,
+        width: window_size.x as u32,
+        height: window_size.y as u32,
         ..Default::default()
     };
 
     // This is the texture that will be rendered to.
-    let mut image = Image {
-        texture_descriptor: TextureDescriptor {
-            label: None,
-            size,
-            dimension: TextureDimension::D2,
-            format: TextureFormat::bevy_default(),
-            mip_level_count: 1,
-            sample_count: 1,
-            usage: TextureUsages::TEXTURE_BINDING
-                | TextureUsages::COPY_DST
-                | TextureUsages::RENDER_ATTACHMENT,
-        },
-        ..default()
-    };
-
-    // Fill image.data with zeroes
-    image.resize(size);
+    let image_handle = images.add(render_target_image(size));
 
-    let image_handle = images.add(image);
+    // This is the texture the lights will be rendered to.
+    let light_map_handle = images.add(render_target_image(size));
 
     // Add main camera rendering to the image
     commands
@@ -90,6 +89,27 @@ fn camera_setup(
         .insert(Name::new("MainCamera"))
         .insert(MainCamera);
 
+    // The lights are drawn on their own layer on top of the ambient darkness
+    let lighting_layer = RenderLayers::layer((RenderLayers::TOTAL_LAYERS - 2) as u8);
+
+    commands
+        .spawn_bundle(Camera2dBundle {
+            camera: Camera {
+                target: RenderTarget::Image(light_map_handle.clone()),
+                // renders before the main camera
+                priority: -1,
+                ..Default::default()
+            },
+            camera_2d: Camera2d {
+                clear_color: ClearColorConfig::Custom(Color::BLACK),
+            },
+            ..Default::default()
+        })
+        .insert(UiCameraConfig { show_ui: false })
+        .insert(lighting_layer)
+        .insert(Name::new("LightCamera"))
+        .insert(LightCamera);
+
     // This specifies the layer used for the post processing camera, which will be attached to the post processing camera and 2d quad.
     let post_processing_pass_layer = RenderLayers::layer((RenderLayers::TOTAL_LAYERS - 1) as u8);
 
@@ -98,6 +118,7 @@ fn camera_setup(
     // This material has the texture that has been rendered.
     let material_handle = post_processing_materials.add(PixeliseMaterial {
         source_image: image_handle.clone(),
+        light_map: light_map_handle.clone(),
     });
 
     set_post_processing_effects(
@@ -110,6 +131,8 @@ fn camera_setup(
 
     commands.insert_resource(post_processing_pass_layer_resource);
     commands.insert_resource(CameraRenderImage(image_handle));
+    commands.insert_resource(LightMapImage(light_map_handle));
+    commands.insert_resource(LightingLayer(lighting_layer));
 
     // The post-processing pass camera.
     commands
@@ -125,6 +148,36 @@ fn camera_setup(
         .insert(Name::new("SecondaryCamera"));
 }
 
+fn render_target_image(size: Extent3d) -> Image {
+    let mut image = Image {
+        texture_descriptor: TextureDescriptor {
+            label: None,
+            size,
+            dimension: TextureDimension::D2,
+            format: TextureFormat::bevy_default(),
+            mip_level_count: 1,
+            sample_count: 1,
+            usage: TextureUsages::TEXTURE_BINDING
+                | TextureUsages::COPY_DST
+                | TextureUsages::RENDER_ATTACHMENT,
+        },
+        ..default()
+    };
+
+    // Fill image.data with zeroes
+    image.resize(size);
+
+    image
+}
+
+/// Size of the primary window in logical pixels
+pub fn screen_size(windows: &Windows) -> Vec2 {
+    windows
+        .get_primary()
+        .map(|window| Vec2::new(window.width(), window.height()))
+        .unwrap_or(HEADLESS_SCREEN_SIZE)
+}
+
 fn set_post_processing_effects<M: Material2d>(
     commands: &mut Commands,
     material: Handle<M>,
@@ -132,9 +185,7 @@ fn set_post_processing_effects<M: Material2d>(
     meshes: &mut ResMut<Assets<Mesh>>,
     post_processing_pass_layer: &PostProcessingLayer,
 ) {
-    let window = windows.primary();
-
-    let size = Vec2::new(window.width(), window.height());
+    let size = screen_size(windows);
 
     let quad_handle = meshes.add(Mesh::from(shape::Quad::new(size)));
 
@@ -1,5 +1,6 @@
-use bevy::prelude::*;
+use bevy::{prelude::*, utils::HashMap};
 use bevy_inspector_egui::Inspectable;
+use serde::Deserialize;
 
 pub struct HealthPlugin;
 
@@ -16,6 +17,8 @@ impl Plugin for HealthPlugin {
 pub struct Health {
     health: f32,
     max_health: f32,
+    /// Fraction of the damage that gets blocked, set by status effects
+    damage_reduction: f32,
 }
 
 impl Health {
@@ -23,6 +26,7 @@ impl Health {
         Self {
             health: max_health,
             max_health,
+            damage_reduction: 0.,
         }
     }
 
@@ -31,7 +35,7 @@ impl Health {
     #[allow(unused)]
     #[deny(unused_results)]
     pub fn take_damage(&mut self, amount: f32) -> bool {
-        self.health -= amount;
+        self.health -= amount * (1. - self.damage_reduction);
 
         self.health <= 0.0
     }
@@ -45,6 +49,22 @@ impl Health {
         }
     }
 
+    /// Raises the max health and heals the player by the same amount
+    pub fn raise_max_health(&mut self, amount: f32) {
+        self.max_health += amount;
+        self.health += amount;
+    }
+
+    pub fn set_damage_reduction(&mut self, damage_reduction: f32) {
+        self.damage_reduction = damage_reduction.clamp(0., 1.);
+    }
+
+    /// Overwrites the health, used for the state received over the network
+    #[allow(unused)]
+    pub fn set_health(&mut self, health: f32) {
+        self.health = health.min(self.max_health);
+    }
+
     #[allow(unused)]
     pub fn get_health(&self) -> f32 {
         self.health
@@ -56,14 +76,118 @@ impl Health {
     }
 }
 
+/// What kind of damage was dealt, enemies can resist some kinds better than others
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum DamageKind {
+    Laser,
+    Piercing,
+    Burn,
+    /// From explosive barrels
+    Explosion,
+    /// From electrified floor tiles
+    Shock,
+}
+
+impl DamageKind {
+    /// Whether the damage comes straight from the beam of a weapon
+    pub fn from_beam(&self) -> bool {
+        matches!(self, DamageKind::Laser | DamageKind::Piercing)
+    }
+}
+
+/// Where a hit came from
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct DamageSource {
+    pub kind: DamageKind,
+    /// The entity that dealt the damage, e.g. the weapon of a player
+    pub entity: Entity,
+}
+
+/// Fraction of the damage of each kind that is ignored, 1 means immune
+#[derive(Component, Default, Deref, DerefMut)]
+pub struct Resistances(pub HashMap<DamageKind, f32>);
+
+impl Resistances {
+    /// # Returns
+    /// The amount of damage left after the resistance to the given kind.
+    pub fn apply(&self, kind: DamageKind, amount: f32) -> f32 {
+        let resistance = self.get(&kind).copied().unwrap_or(0.).clamp(0., 1.);
+
+        amount * (1. - resistance)
+    }
+}
+
 #[derive(Default, Reflect, Inspectable, Component)]
 #[reflect(Component)]
 pub struct Movement {
+    /// The top speed when moving on its own
     pub speed: f32,
+    /// Added to the speed as a fraction of it, set by status effects
+    pub speed_modifier: f32,
+    /// How fast the velocity gets up to speed, per second
+    pub acceleration: f32,
+    /// How fast the velocity goes back down when stopping or after a knockback, per second
+    pub deceleration: f32,
+    /// Heavier things get knocked back less
+    pub mass: f32,
+    pub velocity: Vec2,
+}
+
+impl Movement {
+    pub fn new(speed: f32) -> Self {
+        Self {
+            speed,
+            speed_modifier: 0.,
+            acceleration: speed * 8.,
+            deceleration: speed * 10.,
+            mass: 1.,
+            velocity: Vec2::ZERO,
+        }
+    }
+
+    pub fn with_acceleration(mut self, acceleration: f32, deceleration: f32) -> Self {
+        self.acceleration = acceleration;
+        self.deceleration = deceleration;
+        self
+    }
+
+    pub fn with_mass(mut self, mass: f32) -> Self {
+        self.mass = mass;
+        self
+    }
+
+    pub fn current_speed(&self) -> f32 {
+        (self.speed * (1. + self.speed_modifier)).max(0.)
+    }
+
+    /// Steers the velocity towards moving in the given direction at full speed, or towards standing still
+    pub fn accelerate(&mut self, direction: Option<Vec2>, delta_seconds: f32) {
+        let target = direction.unwrap_or(Vec2::ZERO).clamp_length_max(1.) * self.current_speed();
+
+        let rate = if target == Vec2::ZERO {
+            self.deceleration
+        } else {
+            self.acceleration
+        };
+
+        let difference = target - self.velocity;
+        let step = rate * delta_seconds;
+
+        self.velocity = if difference.length() <= step {
+            target
+        } else {
+            self.velocity + difference.normalize() * step
+        };
+    }
+
+    pub fn knock_back(&mut self, impulse: Vec2) {
+        self.velocity += impulse / self.mass.max(0.01);
+    }
 }
 
 #[derive(Default, Reflect, Inspectable, Component)]
 #[reflect(Component)]
 pub struct Rotation {
+    /// Radians per second
     pub rotation_speed: f32,
 }
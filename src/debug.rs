@@ -1,4 +1,4 @@
-use bevy::prelude::*;
+use bevy::{prelude::*, render::settings::WgpuSettings};
 use bevy_inspector_egui::WorldInspectorPlugin;
 use bevy_prototype_debug_lines::DebugLinesPlugin;
 
@@ -6,7 +6,13 @@ pub struct DebugPlugin;
 
 impl Plugin for DebugPlugin {
     fn build(&self, app: &mut App) {
-        if cfg!(debug_assertions) {
+        // Both draw through the renderer, which a headless server runs without
+        let renders = app
+            .world
+            .get_resource::<WgpuSettings>()
+            .map_or(true, |settings| settings.backends.is_some());
+
+        if cfg!(debug_assertions) && renders {
             app.add_plugin(WorldInspectorPlugin::new())
                 .add_plugin(DebugLinesPlugin::default());
         }
@@ -1,16 +1,19 @@
 use std::time::Duration;
 
+use crate::barricade::Breaching;
 use crate::game_area::EnemySpawn;
 use crate::loading::{AudioAssets, TextureAssets};
-use crate::score::Score;
+use crate::network::NetworkRole;
+use crate::projectile::ProjectileBundle;
+use crate::status::{StatusEffectDefinition, StatusEffects, StatusKind};
 use crate::weapon::EntityHitEvent;
 use crate::{
-    character::{Health, Movement},
-    player::Player,
+    character::{DamageSource, Health, Movement, Resistances},
+    player::{Dash, Player, PlayerDamagedEvent},
 };
-use crate::{GameState, WALL_WIDTH};
+use crate::{GameState, GAME_AREA_HEIGHT, GAME_AREA_WIDTH, WALL_HEIGHT, WALL_WIDTH};
 use bevy::math::Vec3Swizzles;
-use bevy::{prelude::*, sprite::collide_aabb::collide};
+use bevy::prelude::*;
 use bevy_kira_audio::{Audio, AudioControl};
 use bevy_rapier2d::prelude::*;
 use rand::seq::SliceRandom;
@@ -20,11 +23,13 @@ pub struct EnemyPlugin;
 
 impl Plugin for EnemyPlugin {
     fn build(&self, app: &mut App) {
-        app.add_system_set(SystemSet::on_enter(GameState::Playing).with_system(enemy_spawn_setup))
+        app.add_event::<EnemyDamagedEvent>()
+            .add_system_set(SystemSet::on_enter(GameState::Playing).with_system(enemy_spawn_setup))
             .add_system_set(
                 SystemSet::on_update(GameState::Playing)
                     .with_system(hit_player)
                     .with_system(spawn_enemies)
+                    .with_system(flash_spawn_warnings)
                     .with_system(follow_player)
                     .with_system(take_damage)
                     .with_system(enemy_growl),
@@ -38,31 +43,118 @@ impl Plugin for EnemyPlugin {
 }
 
 #[derive(Component)]
-struct Enemy;
+pub struct Enemy;
+
+pub struct EnemyDamagedEvent {
+    /// Already despawned if the enemy got killed
+    pub enemy: Entity,
+    pub amount: f32,
+    pub position: Vec2,
+    pub killed: bool,
+    /// Critical hits are shown in a different color
+    pub critical: bool,
+    /// The last hit that went into the damage
+    pub source: DamageSource,
+}
 
-#[derive(Component, Deref, DerefMut)]
-struct AttackTimer(Timer);
+/// Score for killing an enemy
+pub const ENEMY_KILL_SCORE: u32 = 100;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum AttackKind {
+    /// Hits the player when touching them
+    Melee,
+    /// Fires projectiles at the player from a distance
+    Ranged { projectile_speed: f32 },
+}
+
+#[derive(Component)]
+pub struct Attack {
+    pub damage: f32,
+    /// How close the player has to be for the enemy to attack
+    pub range: f32,
+    pub cooldown: Timer,
+    pub kind: AttackKind,
+}
+
+impl Attack {
+    /// How close the enemy tries to get to the player
+    fn keep_distance(&self) -> f32 {
+        match self.kind {
+            AttackKind::Melee => self.range * 0.75,
+            AttackKind::Ranged { .. } => self.range * RANGED_ENEMY_KEEP_DISTANCE,
+        }
+    }
+}
 
+/// Runs from the last time the enemy reported its damage
 #[derive(Component, Deref, DerefMut)]
 struct HitTimer(Timer);
 
+/// Damage taken since the last [EnemyDamagedEvent], reported in ticks so the damage numbers stay readable
+#[derive(Component, Default)]
+struct PendingDamage {
+    amount: f32,
+    source: Option<DamageSource>,
+    /// Some of the damage went into a [WeakPoint]
+    critical: bool,
+}
+
+/// Part of an enemy taking more damage than the rest of it, the damage goes to the parent enemy
+#[derive(Component)]
+pub struct WeakPoint {
+    pub multiplier: f32,
+}
+
 #[derive(Component, Deref, DerefMut)]
 struct GrowlTimer(Timer);
 
 #[derive(Deref, DerefMut)]
 struct EnemySpawnTimer(Timer);
 
+/// The spawn point the next enemy will come from, chosen ahead of time so it can be telegraphed
+#[derive(Deref, DerefMut)]
+struct NextEnemySpawn(Option<Entity>);
+
+/// Makes an enemy spawn window flash until the enemy comes through it
+#[derive(Component, Deref, DerefMut)]
+struct SpawnWarning(Timer);
+
 pub const ENEMY_SPAWN_TIME_DEFAULT: f32 = 5.;
 pub const ENEMY_SPAWN_TIME_INCREASE_RATE: f32 = 0.95;
 pub const ENEMY_SPAWN_TIME_MINIMUM: f32 = 0.5;
+pub const ENEMY_SPAWN_WARNING_TIME: f32 = 1.;
+
+pub const MELEE_ATTACK_RANGE: f32 = 32.;
+pub const RANGED_ATTACK_RANGE: f32 = 350.;
+/// Part of the attack range ranged enemies stop at
+pub const RANGED_ENEMY_KEEP_DISTANCE: f32 = 0.7;
+/// Chance of a spawned enemy being a ranged one
+pub const RANGED_ENEMY_CHANCE: f64 = 0.25;
+pub const PROJECTILE_SPEED: f32 = 250.;
+
+/// Push given to enemies in a laser, per second
+pub const BEAM_KNOCKBACK: f32 = 300.;
+/// Push given to players hit by a melee attack
+pub const ATTACK_KNOCKBACK: f32 = 250.;
+
+/// Enemies move slower while getting shot
+pub const HIT_SLOW: StatusEffectDefinition = StatusEffectDefinition {
+    kind: StatusKind::Slow,
+    strength: 0.5,
+    duration: 0.1,
+};
 
 #[derive(Bundle)]
 pub struct EnemyBundle {
     health: Health,
     movement: Movement,
     enemy: Enemy,
-    attack_timer: AttackTimer,
+    attack: Attack,
     hit_timer: HitTimer,
+    pending_damage: PendingDamage,
+    resistances: Resistances,
+    status_effects: StatusEffects,
     growl_timer: GrowlTimer,
     collider: Collider,
     name: Name,
@@ -70,15 +162,60 @@ pub struct EnemyBundle {
     sprite: SpriteBundle,
 }
 
+impl EnemyBundle {
+    pub fn at(position: Vec2, textures: &TextureAssets) -> Self {
+        Self {
+            sprite: SpriteBundle {
+                texture: textures.enemy_texture.clone(),
+                transform: Transform {
+                    translation: position.extend(2.),
+                    scale: Vec3::new(0.25, 0.25, 1.),
+                    ..default()
+                },
+                ..default()
+            },
+            ..default()
+        }
+    }
+
+    /// An enemy that keeps its distance and shoots at the player
+    pub fn ranged(position: Vec2, textures: &TextureAssets) -> Self {
+        let mut enemy = Self::at(position, textures);
+
+        enemy.health = Health::new(60.);
+        enemy.movement = Movement::new(60.);
+        enemy.attack = Attack {
+            damage: 8.,
+            range: RANGED_ATTACK_RANGE,
+            cooldown: Timer::from_seconds(2.5, false),
+            kind: AttackKind::Ranged {
+                projectile_speed: PROJECTILE_SPEED,
+            },
+        };
+        enemy.sprite.sprite.color = Color::rgb(0.6, 1., 0.6);
+        enemy.name = Name::new("Ranged Enemy");
+
+        enemy
+    }
+}
+
 impl Default for EnemyBundle {
     fn default() -> Self {
         Self {
             health: Health::new(100.),
-            movement: Movement { speed: 75. },
+            movement: Movement::new(75.),
             enemy: Enemy,
             collider: Collider::cuboid(64., 64.),
-            attack_timer: AttackTimer(Timer::from_seconds(2., false)),
+            attack: Attack {
+                damage: 10.,
+                range: MELEE_ATTACK_RANGE,
+                cooldown: Timer::from_seconds(2., false),
+                kind: AttackKind::Melee,
+            },
             hit_timer: HitTimer(Timer::from_seconds(0.1, false)),
+            pending_damage: PendingDamage::default(),
+            resistances: Resistances::default(),
+            status_effects: StatusEffects::default(),
             growl_timer: GrowlTimer(Timer::from_seconds(
                 rand::thread_rng().gen_range(2.0..15.),
                 false,
@@ -91,31 +228,49 @@ impl Default for EnemyBundle {
 
 fn follow_player(
     player_query: Query<&Transform, (With<Player>, Without<Enemy>)>,
-    mut enemies_query: Query<(&mut Transform, &Movement, &HitTimer), With<Enemy>>,
+    mut enemies_query: Query<
+        (&mut Transform, &mut Movement, &Attack),
+        (With<Enemy>, Without<Breaching>),
+    >,
     time: Res<Time>,
 ) {
-    let player_transform = player_query.single();
-    let player_translation = player_transform.translation.xy();
+    for (mut enemy_transform, mut movement, attack) in enemies_query.iter_mut() {
+        // Go after the nearest living player
+        let enemy_translation = enemy_transform.translation;
+        let player_transform = match player_query.iter().min_by(|a, b| {
+            a.translation
+                .distance_squared(enemy_translation)
+                .total_cmp(&b.translation.distance_squared(enemy_translation))
+        }) {
+            Some(player_transform) => player_transform,
+            None => return,
+        };
+        let player_translation = player_transform.translation.xy();
 
-    for (mut enemy_transform, movement, hit_timer) in enemies_query.iter_mut() {
         let to_player = (player_translation - enemy_transform.translation.xy()).normalize();
         enemy_transform.rotation = Quat::from_rotation_arc(Vec3::Y, to_player.extend(0.));
 
-        if collide(
-            enemy_transform.translation,
-            Vec2::splat(16.),
-            player_transform.translation,
-            Vec2::splat(32.),
-        )
-        .is_none()
-        {
-            let forward = enemy_transform.up();
-            enemy_transform.translation += forward
-                * time.delta_seconds()
-                * movement.speed
-                // Move slower when getting shot
-                * if !hit_timer.finished() { 0.5 } else { 1. };
-        }
+        let distance = enemy_transform
+            .translation
+            .xy()
+            .distance(player_translation);
+
+        let direction = if distance > attack.keep_distance() {
+            Some(to_player)
+        } else {
+            None
+        };
+
+        movement.accelerate(direction, time.delta_seconds());
+        enemy_transform.translation += (movement.velocity * time.delta_seconds()).extend(0.);
+
+        // Don't get knocked back through the walls
+        let bounds = Vec2::new(
+            GAME_AREA_WIDTH / 2. - WALL_WIDTH,
+            GAME_AREA_HEIGHT / 2. - WALL_HEIGHT,
+        );
+        let clamped = enemy_transform.translation.xy().clamp(-bounds, bounds);
+        enemy_transform.translation = clamped.extend(enemy_transform.translation.z);
     }
 }
 
@@ -124,16 +279,49 @@ fn enemy_spawn_setup(mut commands: Commands) {
         ENEMY_SPAWN_TIME_DEFAULT,
         false,
     )));
+    commands.insert_resource(NextEnemySpawn(None));
 }
 
 fn spawn_enemies(
     mut commands: Commands,
-    enemy_spawn_points: Query<&Transform, With<EnemySpawn>>,
+    enemy_spawn_points: Query<(Entity, &Transform), With<EnemySpawn>>,
     mut enemy_spawn_timer: ResMut<EnemySpawnTimer>,
+    mut next_enemy_spawn: ResMut<NextEnemySpawn>,
     time: Res<Time>,
     textures: Res<TextureAssets>,
+    network_role: Res<NetworkRole>,
 ) {
-    if !enemy_spawn_timer.tick(time.delta()).just_finished() {
+    // Clients get their enemies from the host
+    if !network_role.is_authority() {
+        return;
+    }
+
+    let choose_spawn_point = || {
+        enemy_spawn_points
+            .iter()
+            .map(|(entity, _)| entity)
+            .collect::<Vec<Entity>>()
+            .choose(&mut rand::thread_rng())
+            .copied()
+            .expect("There are no enemy spawn points on the map")
+    };
+
+    enemy_spawn_timer.tick(time.delta());
+
+    let time_left = enemy_spawn_timer.duration().as_secs_f32() - enemy_spawn_timer.elapsed_secs();
+
+    // Telegraph the spawn point shortly before the enemy comes through it
+    if next_enemy_spawn.is_none() && time_left <= ENEMY_SPAWN_WARNING_TIME {
+        let spawn_point = choose_spawn_point();
+
+        commands
+            .entity(spawn_point)
+            .insert(SpawnWarning(Timer::from_seconds(time_left.max(0.1), false)));
+
+        next_enemy_spawn.0 = Some(spawn_point);
+    }
+
+    if !enemy_spawn_timer.just_finished() {
         return;
     }
 
@@ -146,92 +334,242 @@ fn spawn_enemies(
     enemy_spawn_timer.set_duration(Duration::from_secs_f32(new_duration));
     enemy_spawn_timer.reset();
 
-    // Choose a random spawn point
-    let spawn_points = enemy_spawn_points.iter().collect::<Vec<&Transform>>();
+    let spawn_point = next_enemy_spawn.take().unwrap_or_else(choose_spawn_point);
 
-    let spawn_point = spawn_points
-        .choose(&mut rand::thread_rng())
-        .expect("There are no enemy spawn points on the map");
+    let (window, spawn_point) = enemy_spawn_points
+        .get(spawn_point)
+        .expect("The enemy spawn point has disappeared");
 
-    let position = spawn_point.translation.truncate() + spawn_point.up().truncate() * WALL_WIDTH;
+    let position = spawn_point_position(spawn_point);
 
-    commands.spawn_bundle(EnemyBundle {
-        sprite: SpriteBundle {
-            texture: textures.enemy_texture.clone(),
-            transform: Transform {
-                translation: position.extend(2.),
-                scale: Vec3::new(0.25, 0.25, 1.),
-                ..default()
-            },
-            ..default()
-        },
-        ..default()
-    });
+    let mut enemy = if rand::thread_rng().gen_bool(RANGED_ENEMY_CHANCE) {
+        commands.spawn_bundle(EnemyBundle::ranged(position, &textures))
+    } else {
+        commands.spawn_bundle(EnemyBundle::at(position, &textures))
+    };
+
+    // It has to get through the boards of the window first
+    enemy.insert(Breaching { window });
+}
+
+/// Where enemies coming through the given spawn point appear
+pub fn spawn_point_position(spawn_point: &Transform) -> Vec2 {
+    spawn_point.translation.truncate() + spawn_point.up().truncate() * WALL_WIDTH
+}
+
+fn flash_spawn_warnings(
+    mut commands: Commands,
+    mut spawn_points: Query<(Entity, &mut Sprite, &mut SpawnWarning)>,
+    time: Res<Time>,
+) {
+    for (entity, mut sprite, mut warning) in spawn_points.iter_mut() {
+        if warning.tick(time.delta()).finished() {
+            sprite.color = Color::WHITE;
+            commands.entity(entity).remove::<SpawnWarning>();
+            continue;
+        }
+
+        let flash_on = (warning.elapsed_secs() * 8.) as u32 % 2 == 0;
+
+        sprite.color = if flash_on {
+            Color::rgb(1., 0.25, 0.25)
+        } else {
+            Color::WHITE
+        };
+    }
 }
 
 fn enemy_spawn_cleanup(mut commands: Commands) {
     commands.remove_resource::<EnemySpawnTimer>();
+    commands.remove_resource::<NextEnemySpawn>();
 }
 
 fn take_damage(
     mut commands: Commands,
-    mut enemies: Query<(Entity, &mut HitTimer, &mut Health), With<Enemy>>,
+    mut enemies: Query<
+        (
+            Entity,
+            &Transform,
+            &Resistances,
+            &mut HitTimer,
+            &mut PendingDamage,
+            &mut Health,
+            &mut StatusEffects,
+            &mut Movement,
+        ),
+        With<Enemy>,
+    >,
+    weak_points: Query<(&WeakPoint, &Parent)>,
     mut entity_hit_event_reader: EventReader<EntityHitEvent>,
+    mut enemy_damaged_event_writer: EventWriter<EnemyDamagedEvent>,
     time: Res<Time>,
-    mut score: ResMut<Score>,
+    network_role: Res<NetworkRole>,
 ) {
-    let entity_hits = entity_hit_event_reader.iter().collect::<Vec<_>>();
+    // Hits are resolved on the host
+    if !network_role.is_authority() {
+        return;
+    }
 
-    for (enemy_entity, mut hit_timer, mut health) in enemies.iter_mut() {
-        hit_timer.tick(time.delta());
+    for hit in entity_hit_event_reader.iter() {
+        let (target, multiplier) = match weak_points.get(hit.entity) {
+            Ok((weak_point, parent)) => (parent.get(), weak_point.multiplier),
+            Err(_) => (hit.entity, 1.),
+        };
+
+        let (
+            enemy_entity,
+            transform,
+            resistances,
+            mut hit_timer,
+            mut pending_damage,
+            mut health,
+            mut status_effects,
+            mut movement,
+        ) = match enemies.get_mut(target) {
+            Ok(enemy) => enemy,
+            Err(_) => continue,
+        };
+
+        // Already killed by another ray this frame
+        if health.get_health() <= 0. {
+            continue;
+        }
 
-        for hit in &entity_hits {
-            if enemy_entity.id() == hit.0.id() {
-                if hit_timer.finished() {
-                    // TODO: Maybe change it from a hard-coded value to a component
-                    if health.take_damage(50.) {
-                        commands.entity(enemy_entity).despawn_recursive();
-                        score.0 += 100;
-                    }
+        let amount = resistances.apply(hit.source.kind, hit.amount) * multiplier;
+        let killed = health.take_damage(amount);
 
-                    info!("remaining_health={:?}", health.get_health());
+        pending_damage.amount += amount;
+        pending_damage.source = Some(hit.source);
+        pending_damage.critical |= multiplier > 1.;
 
-                    hit_timer.reset();
-                }
-            }
+        // Burning doesn't slow enemies down or push them, only the lasers do
+        if hit.source.kind.from_beam() {
+            status_effects.apply(&HIT_SLOW, None);
+
+            let away_from_laser = (transform.translation.xy() - hit.point).normalize_or_zero();
+            movement.knock_back(away_from_laser * BEAM_KNOCKBACK * time.delta_seconds());
+        }
+
+        if killed {
+            commands.entity(enemy_entity).despawn_recursive();
+
+            enemy_damaged_event_writer.send(EnemyDamagedEvent {
+                enemy: enemy_entity,
+                amount: pending_damage.amount,
+                position: transform.translation.truncate(),
+                killed,
+                critical: pending_damage.critical,
+                source: hit.source,
+            });
+
+            hit_timer.reset();
         }
     }
+
+    for (enemy_entity, transform, _, mut hit_timer, mut pending_damage, health, ..) in
+        enemies.iter_mut()
+    {
+        hit_timer.tick(time.delta());
+
+        if !hit_timer.finished() || health.get_health() <= 0. {
+            continue;
+        }
+
+        let source = match pending_damage.source.take() {
+            Some(source) => source,
+            None => continue,
+        };
+
+        enemy_damaged_event_writer.send(EnemyDamagedEvent {
+            enemy: enemy_entity,
+            amount: pending_damage.amount,
+            position: transform.translation.truncate(),
+            killed: false,
+            critical: pending_damage.critical,
+            source,
+        });
+
+        info!("remaining_health={:?}", health.get_health());
+
+        pending_damage.amount = 0.;
+        pending_damage.critical = false;
+        hit_timer.reset();
+    }
 }
 
 fn hit_player(
-    mut enemy_query: Query<(&Transform, &mut AttackTimer), With<Enemy>>,
-    mut player_query: Query<(&Transform, &mut Health), With<Player>>,
+    mut commands: Commands,
+    mut enemy_query: Query<
+        (&Transform, &mut Attack, &StatusEffects),
+        (With<Enemy>, Without<Breaching>),
+    >,
+    mut player_query: Query<(&Transform, &mut Health, &Dash, &mut Movement), With<Player>>,
+    mut player_damaged_events: EventWriter<PlayerDamagedEvent>,
     time: Res<Time>,
     audio: Res<Audio>,
     sounds: Res<AudioAssets>,
+    network_role: Res<NetworkRole>,
 ) {
-    let (player_transform, mut player_health) = player_query.single_mut();
-
-    for (enemy_transform, mut attack_timer) in enemy_query.iter_mut() {
-        attack_timer.tick(time.delta());
-
-        if collide(
-            player_transform.translation,
-            Vec2::splat(32.),
-            enemy_transform.translation,
-            Vec2::splat(32.),
-        )
-        .is_some()
-            && attack_timer.finished()
-        {
-            // TODO: Make AttackDamage component?
-            player_health.take_damage(10.);
-            attack_timer.reset();
-
-            if let Some(attack) = sounds.attacks.choose(&mut rand::thread_rng()) {
-                audio.play(attack.clone()).with_volume(0.3);
+    if !network_role.is_authority() {
+        return;
+    }
+
+    for (enemy_transform, mut attack, status_effects) in enemy_query.iter_mut() {
+        if !attack.cooldown.tick(time.delta()).finished() || status_effects.has(StatusKind::Stun) {
+            continue;
+        }
+
+        let enemy_position = enemy_transform.translation.xy();
+
+        // Attack the nearest player in range
+        let (player_transform, mut player_health, dash, mut player_movement) = match player_query
+            .iter_mut()
+            .filter(|(transform, ..)| {
+                transform.translation.xy().distance(enemy_position) <= attack.range
+            })
+            .min_by(|(a, ..), (b, ..)| {
+                a.translation
+                    .xy()
+                    .distance_squared(enemy_position)
+                    .total_cmp(&b.translation.xy().distance_squared(enemy_position))
+            }) {
+            Some(player) => player,
+            None => continue,
+        };
+
+        match attack.kind {
+            AttackKind::Melee => {
+                // Dashing players can't be hit, the attack waits for them
+                if dash.is_invulnerable() {
+                    continue;
+                }
+
+                player_health.take_damage(attack.damage);
+                player_damaged_events.send(PlayerDamagedEvent {
+                    amount: attack.damage,
+                });
+
+                let away_from_enemy =
+                    (player_transform.translation.xy() - enemy_position).normalize_or_zero();
+                player_movement.knock_back(away_from_enemy * ATTACK_KNOCKBACK);
+
+                if let Some(attack) = sounds.attacks.choose(&mut rand::thread_rng()) {
+                    audio.play(attack.clone()).with_volume(0.3);
+                }
+            }
+            AttackKind::Ranged { projectile_speed } => {
+                let direction =
+                    (player_transform.translation.xy() - enemy_position).normalize_or_zero();
+
+                commands.spawn_bundle(ProjectileBundle::new(
+                    enemy_position + direction * 20.,
+                    direction * projectile_speed,
+                    attack.damage,
+                ));
             }
         }
+
+        attack.cooldown.reset();
     }
 }
 
@@ -1,18 +1,49 @@
 use bevy::prelude::*;
 use bevy_rapier2d::prelude::Collider;
-use rand::{distributions::Standard, prelude::Distribution};
+use rand::{distributions::Standard, prelude::Distribution, rngs::StdRng, Rng, SeedableRng};
 
 use crate::{
-    loading::TextureAssets, GameState, GAME_AREA_HEIGHT, GAME_AREA_WIDTH, WALL_HEIGHT, WALL_WIDTH,
+    character::Health,
+    hazards::{
+        ElectrifiedTileBundle, ExplosiveBarrelBundle, ELECTRIFIED_OFF_TIME, ELECTRIFIED_ON_TIME,
+    },
+    lighting::{LightOccluder, PointLight2d},
+    loading::TextureAssets,
+    network::NetworkRole,
+    weapon::EntityHitEvent,
+    GameState, GAME_AREA_HEIGHT, GAME_AREA_WIDTH, WALL_HEIGHT, WALL_WIDTH,
 };
 
 pub const ENEMY_SPAWN_NUMBER: u32 = 6;
 
+/// Chance of a wall tile being one the beam can break through
+pub const DESTRUCTIBLE_WALL_CHANCE: f64 = 0.6;
+pub const WALL_HEALTH: f32 = 600.;
+/// Beam damage it takes to heat a wall up all the way
+pub const WALL_HEAT_CAPACITY: f32 = 1000.;
+/// Heat a wall loses per second
+pub const WALL_COOLING_RATE: f32 = 0.35;
+/// Walls show cracks below this fraction of their health
+pub const WALL_CRACK_THRESHOLD: f32 = 0.5;
+
+pub const EXPLOSIVE_BARRELS: usize = 5;
+pub const ELECTRIFIED_TILES: usize = 3;
+/// Hazards are kept at least this far from the middle, where the players start
+pub const HAZARD_CLEARANCE: f32 = 150.;
+/// And at least this far from the walls
+pub const HAZARD_MARGIN: f32 = 80.;
+
 pub struct GameAreaPlugin;
 
 impl Plugin for GameAreaPlugin {
     fn build(&self, app: &mut App) {
         app.add_system_set(SystemSet::on_enter(GameState::Playing).with_system(world_setup))
+            .add_system_set(
+                SystemSet::on_update(GameState::Playing)
+                    .with_system(rebuild_arena)
+                    .with_system(heat_walls)
+                    .with_system(glow_walls),
+            )
             .add_system_set(SystemSet::on_exit(GameState::Playing).with_system(drop_game_area));
     }
 }
@@ -20,6 +51,10 @@ impl Plugin for GameAreaPlugin {
 #[derive(Component)]
 pub struct GameArea;
 
+/// Everything random about the arena comes from this, so the host can share it with the clients
+#[derive(Deref, DerefMut)]
+pub struct ArenaSeed(pub u64);
+
 #[derive(PartialEq, Clone, Copy)]
 pub enum Wall {
     Top,
@@ -66,7 +101,21 @@ impl Distribution<EnemySpawnPoint> for Standard {
 }
 
 #[derive(Component)]
-pub struct EnemySpawn;
+pub struct EnemySpawn {
+    /// Place among the spawn windows in the spawn order of the arena,
+    /// which is the same on the host and the clients
+    pub index: usize,
+}
+
+/// A wall tile that heats up while the beam is held on it and breaks after enough of it
+#[derive(Component)]
+pub struct DestructibleWall {
+    /// From cold at 0 to glowing at 1, hotter walls take more of the damage
+    pub heat: f32,
+    /// Place among the destructible walls in the spawn order of the arena,
+    /// which is the same on the host and the clients
+    pub index: usize,
+}
 
 #[derive(PartialEq)]
 enum WallTile {
@@ -101,12 +150,46 @@ fn get_wall_tile(position: i32, face: &Wall, enemy_spawns: &Vec<EnemySpawnPoint>
 }
 
 fn world_setup(mut commands: Commands, textures: Res<TextureAssets>, images: Res<Assets<Image>>) {
+    let seed = rand::random();
+
+    commands.insert_resource(ArenaSeed(seed));
+
+    spawn_arena(&mut commands, &textures, &images, seed);
+}
+
+/// Clients build the arena again once the host tells them the seed of its own, see [crate::network]
+fn rebuild_arena(
+    mut commands: Commands,
+    arena_seed: Res<ArenaSeed>,
+    game_area: Query<Entity, With<GameArea>>,
+    textures: Res<TextureAssets>,
+    images: Res<Assets<Image>>,
+) {
+    if !arena_seed.is_changed() || arena_seed.is_added() {
+        return;
+    }
+
+    for game_area in game_area.iter() {
+        commands.entity(game_area).despawn_recursive();
+    }
+
+    spawn_arena(&mut commands, &textures, &images, arena_seed.0);
+}
+
+fn spawn_arena(
+    commands: &mut Commands,
+    textures: &TextureAssets,
+    images: &Assets<Image>,
+    seed: u64,
+) {
+    let mut rng = StdRng::seed_from_u64(seed);
+
     // Generate enemy spawns
     let mut enemy_spawns = Vec::new();
 
     for _ in 0..ENEMY_SPAWN_NUMBER {
         let enemy_spawn = loop {
-            let result: EnemySpawnPoint = rand::random();
+            let result: EnemySpawnPoint = rng.gen();
 
             if get_wall_tile(result.position, &result.wall, &enemy_spawns) == WallTile::Wall
                 && get_wall_tile(result.position - 1, &result.wall, &enemy_spawns) == WallTile::Wall
@@ -123,6 +206,8 @@ fn world_setup(mut commands: Commands, textures: Res<TextureAssets>, images: Res
     let enemy_spawn_texture = &textures.enemy_spawn_texture;
 
     let mut walls = Vec::new();
+    let mut destructible_walls = 0;
+    let mut spawn_windows = 0;
 
     let mut spawn_wall = |position: i32, face: Wall| {
         let translation = match face {
@@ -192,7 +277,29 @@ fn world_setup(mut commands: Commands, textures: Res<TextureAssets>, images: Res
         }));
 
         if is_enemy_spawn {
-            wall.insert(EnemySpawn);
+            wall.insert(EnemySpawn {
+                index: spawn_windows,
+            })
+            .insert(PointLight2d {
+                color: Color::rgb(0.55, 0.65, 1.),
+                radius: 140.,
+                intensity: 0.6,
+                ..default()
+            });
+
+            spawn_windows += 1;
+        } else {
+            wall.insert(LightOccluder);
+
+            if rng.gen_bool(DESTRUCTIBLE_WALL_CHANCE) {
+                wall.insert(DestructibleWall {
+                    heat: 0.,
+                    index: destructible_walls,
+                })
+                .insert(Health::new(WALL_HEALTH));
+
+                destructible_walls += 1;
+            }
         }
 
         wall.id()
@@ -248,6 +355,39 @@ fn world_setup(mut commands: Commands, textures: Res<TextureAssets>, images: Res
         })
         .id();
 
+    // Spawn the hazards
+    let random_position = |rng: &mut StdRng| loop {
+        let position = Vec2::new(
+            rng.gen_range(-1.0..1.0) * (GAME_AREA_WIDTH / 2. - HAZARD_MARGIN),
+            rng.gen_range(-1.0..1.0) * (GAME_AREA_HEIGHT / 2. - HAZARD_MARGIN),
+        );
+
+        if position.length() > HAZARD_CLEARANCE {
+            break position;
+        }
+    };
+
+    let mut hazards = Vec::new();
+
+    for index in 0..EXPLOSIVE_BARRELS {
+        hazards.push(
+            commands
+                .spawn_bundle(ExplosiveBarrelBundle::at(index, random_position(&mut rng)))
+                .id(),
+        );
+    }
+
+    for _ in 0..ELECTRIFIED_TILES {
+        let position = random_position(&mut rng);
+        let offset = rng.gen_range(0.0..ELECTRIFIED_ON_TIME + ELECTRIFIED_OFF_TIME);
+
+        hazards.push(
+            commands
+                .spawn_bundle(ElectrifiedTileBundle::at(position, offset))
+                .id(),
+        );
+    }
+
     commands
         .spawn()
         // Later move it to an entity containing the entire game area (including floors and windows)
@@ -257,11 +397,68 @@ fn world_setup(mut commands: Commands, textures: Res<TextureAssets>, images: Res
         .insert(ComputedVisibility::default())
         .insert(Transform::default())
         .insert(GlobalTransform::default())
-        .push_children(&[walls_entity, floor]);
+        .push_children(&[walls_entity, floor])
+        .push_children(&hazards);
+}
+
+/// Leaves a hole the beam and the light go through
+pub fn break_wall(commands: &mut Commands, wall: Entity) {
+    commands
+        .entity(wall)
+        .remove::<DestructibleWall>()
+        .remove::<Collider>()
+        .remove::<LightOccluder>()
+        .insert(Visibility { is_visible: false })
+        .insert(Name::new("Broken wall"));
+}
+
+fn heat_walls(
+    mut commands: Commands,
+    mut entity_hit_events: EventReader<EntityHitEvent>,
+    mut walls: Query<(&mut DestructibleWall, &mut Health)>,
+    network_role: Res<NetworkRole>,
+) {
+    // Clients break the walls the host says are broken, see [crate::network]
+    if !network_role.is_authority() {
+        return;
+    }
+
+    for event in entity_hit_events.iter() {
+        let (mut wall, mut health) = match walls.get_mut(event.entity) {
+            Ok(wall) => wall,
+            Err(_) => continue,
+        };
+
+        wall.heat = (wall.heat + event.amount / WALL_HEAT_CAPACITY).min(1.);
+
+        // Cold walls shrug the beam off, it only eats through them once they glow
+        if health.take_damage(event.amount * wall.heat) {
+            break_wall(&mut commands, event.entity);
+        }
+    }
+}
+
+fn glow_walls(mut walls: Query<(&mut DestructibleWall, &Health, &mut Sprite)>, time: Res<Time>) {
+    for (mut wall, health, mut sprite) in walls.iter_mut() {
+        wall.heat = (wall.heat - WALL_COOLING_RATE * time.delta_seconds()).max(0.);
+
+        let cracked = health.get_health() < health.get_max_health() * WALL_CRACK_THRESHOLD;
+        let base = if cracked { 0.6 } else { 1. };
+
+        // From the plain texture to a red hot glow
+        sprite.color = Color::rgb(
+            base + (1. - base) * wall.heat,
+            base + (0.35 - base) * wall.heat,
+            base + (0.1 - base) * wall.heat,
+        );
+    }
 }
 
 fn drop_game_area(mut commands: Commands, game_area: Query<Entity, With<GameArea>>) {
     for game_area in game_area.iter() {
         commands.entity(game_area).despawn_recursive();
     }
+
+    // The next run picks a new one
+    commands.remove_resource::<ArenaSeed>();
 }
@@ -1,5 +1,7 @@
 use crate::loading::FontAssets;
-use crate::score::Score;
+use crate::progression::POINTS_PER_CREDIT;
+use crate::score::{Score, ScoreBreakdown};
+use crate::stats::RunStats;
 use crate::GameState;
 use bevy::prelude::*;
 
@@ -21,6 +23,21 @@ impl Plugin for GameOver {
 #[derive(Component)]
 struct GameOverMenu;
 
+#[derive(Component, Clone, Copy)]
+enum GameOverButton {
+    Retry,
+    Menu,
+}
+
+impl GameOverButton {
+    fn label(&self) -> &'static str {
+        match self {
+            GameOverButton::Retry => "Retry",
+            GameOverButton::Menu => "Menu",
+        }
+    }
+}
+
 struct ButtonColors {
     normal: UiColor,
     hovered: UiColor,
@@ -40,6 +57,8 @@ fn setup_menu(
     font_assets: Res<FontAssets>,
     button_colors: Res<ButtonColors>,
     score: Res<Score>,
+    score_breakdown: Res<ScoreBreakdown>,
+    stats: Res<RunStats>,
 ) {
     commands
         .spawn_bundle(NodeBundle {
@@ -80,19 +99,30 @@ fn setup_menu(
             // Your Score
             parent.spawn_bundle(TextBundle {
                 text: Text {
-                    sections: vec![TextSection {
-                        value: format!("Your score: {}", score.0),
-                        style: TextStyle {
-                            font: font_assets.fira_sans.clone(),
-                            font_size: 36.0,
-                            color: Color::WHITE,
+                    sections: vec![
+                        TextSection {
+                            value: format!("Your score: {}", score.0),
+                            style: TextStyle {
+                                font: font_assets.fira_sans.clone(),
+                                font_size: 36.0,
+                                color: Color::WHITE,
+                            },
                         },
-                    }],
+                        // Spent on the upgrade screen
+                        TextSection {
+                            value: format!("  +{} credits", score.0 / POINTS_PER_CREDIT),
+                            style: TextStyle {
+                                font: font_assets.fira_sans.clone(),
+                                font_size: 28.0,
+                                color: Color::GOLD,
+                            },
+                        },
+                    ],
                     alignment: Default::default(),
                 },
                 style: Style {
                     margin: UiRect {
-                        bottom: Val::Percent(5.),
+                        bottom: Val::Percent(1.5),
                         ..default()
                     },
                     ..default()
@@ -100,51 +130,185 @@ fn setup_menu(
                 ..Default::default()
             });
 
-            // Menu button
+            // Summary panel
             parent
-                .spawn_bundle(ButtonBundle {
+                .spawn_bundle(NodeBundle {
                     style: Style {
-                        size: Size::new(Val::Px(120.0), Val::Px(50.0)),
-                        margin: UiRect::all(Val::Auto),
-                        justify_content: JustifyContent::Center,
-                        align_items: AlignItems::Center,
-                        ..Default::default()
+                        align_items: AlignItems::FlexStart,
+                        padding: UiRect::all(Val::Px(15.)),
+                        margin: UiRect {
+                            bottom: Val::Percent(3.),
+                            ..default()
+                        },
+                        ..default()
                     },
-                    color: button_colors.normal,
-                    ..Default::default()
+                    color: Color::rgba(0., 0., 0., 0.5).into(),
+                    ..default()
                 })
                 .with_children(|parent| {
-                    parent.spawn_bundle(TextBundle {
-                        text: Text {
-                            sections: vec![TextSection {
-                                value: "Menu".to_string(),
-                                style: TextStyle {
-                                    font: font_assets.fira_sans.clone(),
-                                    font_size: 40.0,
-                                    color: Color::rgb(0.9, 0.9, 0.9),
-                                },
-                            }],
-                            alignment: Default::default(),
-                        },
-                        ..Default::default()
-                    });
+                    // Where the score came from
+                    spawn_summary_column(
+                        parent,
+                        &font_assets,
+                        score_breakdown
+                            .iter()
+                            .map(|(category, points)| {
+                                (category.name().to_string(), points.to_string())
+                            })
+                            .collect(),
+                    );
+
+                    // How the run went
+                    let time_survived = stats.time_survived.as_secs();
+
+                    spawn_summary_column(
+                        parent,
+                        &font_assets,
+                        vec![
+                            (
+                                "Time survived".to_string(),
+                                format!("{}:{:02}", time_survived / 60, time_survived % 60),
+                            ),
+                            (
+                                "Enemies killed".to_string(),
+                                stats.enemies_killed.to_string(),
+                            ),
+                            (
+                                "Damage dealt".to_string(),
+                                format!("{:.0}", stats.damage_dealt),
+                            ),
+                            (
+                                "Damage taken".to_string(),
+                                format!("{:.0}", stats.damage_taken),
+                            ),
+                            (
+                                "Beam uptime".to_string(),
+                                format!("{:.1}s", stats.beam_uptime.as_secs_f32()),
+                            ),
+                            (
+                                "Beam accuracy".to_string(),
+                                format!("{:.0}%", stats.beam_accuracy() * 100.),
+                            ),
+                            (
+                                "Distance walked".to_string(),
+                                format!("{:.0}", stats.distance_walked),
+                            ),
+                            (
+                                "Most enemies at once".to_string(),
+                                stats.peak_enemy_count.to_string(),
+                            ),
+                        ],
+                    );
+                });
+
+            // Buttons
+            parent
+                .spawn_bundle(NodeBundle {
+                    color: Color::NONE.into(),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    spawn_button(parent, &font_assets, &button_colors, GameOverButton::Retry);
+                    spawn_button(parent, &font_assets, &button_colors, GameOverButton::Menu);
                 });
         })
         .insert(GameOverMenu);
 }
 
+/// A column of label and value pairs
+fn spawn_summary_column(
+    parent: &mut ChildBuilder,
+    font_assets: &FontAssets,
+    rows: Vec<(String, String)>,
+) {
+    parent
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                flex_direction: FlexDirection::ColumnReverse,
+                margin: UiRect::all(Val::Px(10.)),
+                ..default()
+            },
+            color: Color::NONE.into(),
+            ..default()
+        })
+        .with_children(|parent| {
+            for (label, value) in rows {
+                parent.spawn_bundle(TextBundle::from_sections([
+                    TextSection::new(
+                        format!("{}: ", label),
+                        TextStyle {
+                            font: font_assets.fira_sans.clone(),
+                            font_size: 20.0,
+                            color: Color::rgb(0.7, 0.7, 0.7),
+                        },
+                    ),
+                    TextSection::new(
+                        value,
+                        TextStyle {
+                            font: font_assets.fira_sans.clone(),
+                            font_size: 20.0,
+                            color: Color::WHITE,
+                        },
+                    ),
+                ]));
+            }
+        });
+}
+
+fn spawn_button(
+    parent: &mut ChildBuilder,
+    font_assets: &FontAssets,
+    button_colors: &ButtonColors,
+    button: GameOverButton,
+) {
+    parent
+        .spawn_bundle(ButtonBundle {
+            style: Style {
+                size: Size::new(Val::Px(120.0), Val::Px(50.0)),
+                margin: UiRect::all(Val::Px(10.)),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..Default::default()
+            },
+            color: button_colors.normal,
+            ..Default::default()
+        })
+        .with_children(|parent| {
+            parent.spawn_bundle(TextBundle {
+                text: Text {
+                    sections: vec![TextSection {
+                        value: button.label().to_string(),
+                        style: TextStyle {
+                            font: font_assets.fira_sans.clone(),
+                            font_size: 40.0,
+                            color: Color::rgb(0.9, 0.9, 0.9),
+                        },
+                    }],
+                    alignment: Default::default(),
+                },
+                ..Default::default()
+            });
+        })
+        .insert(button);
+}
+
 fn click_play_button(
     button_colors: Res<ButtonColors>,
     mut state: ResMut<State<GameState>>,
     mut interaction_query: Query<
-        (&Interaction, &mut UiColor),
+        (&Interaction, &mut UiColor, &GameOverButton),
         (Changed<Interaction>, With<Button>),
     >,
 ) {
-    for (interaction, mut color) in &mut interaction_query {
+    for (interaction, mut color, button) in &mut interaction_query {
         match *interaction {
             Interaction::Clicked => {
-                state.set(GameState::Menu).unwrap();
+                state
+                    .set(match button {
+                        GameOverButton::Retry => GameState::Playing,
+                        GameOverButton::Menu => GameState::Menu,
+                    })
+                    .unwrap();
             }
             Interaction::Hovered => {
                 *color = button_colors.hovered;
@@ -0,0 +1,359 @@
+use crate::{
+    character::{DamageKind, DamageSource, Health, Movement},
+    enemy::Enemy,
+    lighting::PointLight2d,
+    network::NetworkRole,
+    player::{Dash, Player, PlayerDamagedEvent},
+    weapon::EntityHitEvent,
+    GameState,
+};
+use bevy::{math::Vec3Swizzles, prelude::*};
+use bevy_rapier2d::prelude::*;
+
+pub struct HazardPlugin;
+
+/// This plugin runs the hazards the arena generator puts in the game area.
+/// Explosive barrels blow up when the laser hits them, hurting everything around them,
+/// electrified floor tiles switch on and off and hurt everything standing on them while on.
+impl Plugin for HazardPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<ExplosionEvent>()
+            .add_system_set(SystemSet::on_enter(GameState::Playing).with_system(start_arena_clock))
+            .add_system_set(
+                SystemSet::on_update(GameState::Playing)
+                    .with_system(tick_arena_clock)
+                    .with_system(detonate_barrels)
+                    .with_system(explode)
+                    .with_system(fade_explosions)
+                    .with_system(electrify_tiles),
+            )
+            .add_system_set(SystemSet::on_exit(GameState::Playing).with_system(drop_explosions));
+    }
+}
+
+pub const BARREL_SIZE: f32 = 22.;
+pub const EXPLOSION_RADIUS: f32 = 120.;
+/// Damage right next to the barrel, it falls off to nothing at [EXPLOSION_RADIUS]
+pub const EXPLOSION_DAMAGE: f32 = 120.;
+pub const EXPLOSION_KNOCKBACK: f32 = 500.;
+/// Seconds the flash of an explosion lasts
+pub const EXPLOSION_FLASH_TIME: f32 = 0.4;
+
+pub const ELECTRIFIED_TILE_SIZE: f32 = 75.;
+/// Seconds the tiles stay on and off in each cycle
+pub const ELECTRIFIED_ON_TIME: f32 = 2.;
+pub const ELECTRIFIED_OFF_TIME: f32 = 3.;
+pub const ELECTRIFIED_DAMAGE_PER_SECOND: f32 = 30.;
+
+#[derive(Component)]
+pub struct ExplosiveBarrel {
+    /// Place in the spawn order of the arena, which is the same on the host and the clients
+    pub index: usize,
+}
+
+#[derive(Bundle)]
+pub struct ExplosiveBarrelBundle {
+    barrel: ExplosiveBarrel,
+    collider: Collider,
+    name: Name,
+    #[bundle]
+    sprite: SpriteBundle,
+}
+
+impl ExplosiveBarrelBundle {
+    pub fn at(index: usize, position: Vec2) -> Self {
+        Self {
+            barrel: ExplosiveBarrel { index },
+            collider: Collider::ball(BARREL_SIZE / 2.),
+            name: Name::new("Explosive Barrel"),
+            sprite: SpriteBundle {
+                sprite: Sprite {
+                    color: Color::rgb(0.8, 0.2, 0.1),
+                    custom_size: Some(Vec2::splat(BARREL_SIZE)),
+                    ..default()
+                },
+                transform: Transform::from_translation(position.extend(1.5)),
+                ..default()
+            },
+        }
+    }
+}
+
+/// Seconds the arena has been played in, clients get it from the host so the hazards keep in step
+#[derive(Default, Deref, DerefMut)]
+pub struct ArenaClock(pub f32);
+
+/// Switches on for [ELECTRIFIED_ON_TIME] every cycle, `offset` keeps the tiles from all switching at once
+#[derive(Component)]
+pub struct ElectrifiedTile {
+    offset: f32,
+}
+
+impl ElectrifiedTile {
+    fn is_on(&self, clock: &ArenaClock) -> bool {
+        let cycle = ELECTRIFIED_ON_TIME + ELECTRIFIED_OFF_TIME;
+
+        (clock.0 + self.offset) % cycle < ELECTRIFIED_ON_TIME
+    }
+}
+
+#[derive(Bundle)]
+pub struct ElectrifiedTileBundle {
+    tile: ElectrifiedTile,
+    light: PointLight2d,
+    name: Name,
+    #[bundle]
+    sprite: SpriteBundle,
+}
+
+impl ElectrifiedTileBundle {
+    pub fn at(position: Vec2, offset: f32) -> Self {
+        Self {
+            tile: ElectrifiedTile { offset },
+            light: PointLight2d {
+                color: Color::rgb(0.5, 0.7, 1.),
+                radius: ELECTRIFIED_TILE_SIZE,
+                intensity: 0.,
+                ..default()
+            },
+            name: Name::new("Electrified Tile"),
+            sprite: SpriteBundle {
+                sprite: Sprite {
+                    color: Color::rgba(0.4, 0.7, 1., 0.1),
+                    custom_size: Some(Vec2::splat(ELECTRIFIED_TILE_SIZE)),
+                    ..default()
+                },
+                // Between the floor and everything standing on it
+                transform: Transform::from_translation(position.extend(0.5)),
+                ..default()
+            },
+        }
+    }
+}
+
+/// Sent when a barrel blows up
+pub struct ExplosionEvent {
+    pub position: Vec2,
+    /// What set the barrel off, the damage of the explosion counts as coming from it
+    pub source: DamageSource,
+}
+
+/// The flash left behind by an explosion
+#[derive(Component, Deref, DerefMut)]
+struct ExplosionFlash(Timer);
+
+fn start_arena_clock(mut commands: Commands) {
+    commands.insert_resource(ArenaClock::default());
+}
+
+fn tick_arena_clock(mut clock: ResMut<ArenaClock>, time: Res<Time>) {
+    clock.0 += time.delta_seconds();
+}
+
+fn detonate_barrels(
+    mut commands: Commands,
+    mut entity_hit_events: EventReader<EntityHitEvent>,
+    mut explosion_events: EventWriter<ExplosionEvent>,
+    barrels: Query<&Transform, With<ExplosiveBarrel>>,
+    network_role: Res<NetworkRole>,
+) {
+    // Clients blow up the barrels the host says are gone, see [crate::network]
+    if !network_role.is_authority() {
+        return;
+    }
+
+    // The laser keeps hitting the barrel until it is gone
+    let mut detonated = Vec::new();
+
+    for event in entity_hit_events.iter() {
+        if detonated.contains(&event.entity) {
+            continue;
+        }
+
+        let transform = match barrels.get(event.entity) {
+            Ok(transform) => transform,
+            Err(_) => continue,
+        };
+
+        detonated.push(event.entity);
+        commands.entity(event.entity).despawn_recursive();
+
+        explosion_events.send(ExplosionEvent {
+            position: transform.translation.xy(),
+            source: DamageSource {
+                kind: DamageKind::Explosion,
+                entity: event.source.entity,
+            },
+        });
+    }
+}
+
+fn explode(
+    mut commands: Commands,
+    mut explosion_events: EventReader<ExplosionEvent>,
+    mut entity_hit_events: EventWriter<EntityHitEvent>,
+    mut player_damaged_events: EventWriter<PlayerDamagedEvent>,
+    targets: Query<(Entity, &Transform), Or<(With<Enemy>, With<ExplosiveBarrel>)>>,
+    mut enemies: Query<&mut Movement, (With<Enemy>, Without<Player>)>,
+    mut players: Query<(&Transform, &mut Health, &Dash, &mut Movement), With<Player>>,
+    network_role: Res<NetworkRole>,
+) {
+    for event in explosion_events.iter() {
+        commands
+            .spawn_bundle(SpriteBundle {
+                sprite: Sprite {
+                    color: Color::rgba(1., 0.6, 0.2, 0.6),
+                    custom_size: Some(Vec2::splat(EXPLOSION_RADIUS * 2.)),
+                    ..default()
+                },
+                transform: Transform::from_translation(event.position.extend(3.5)),
+                ..default()
+            })
+            .insert(PointLight2d {
+                color: Color::rgb(1., 0.6, 0.2),
+                radius: EXPLOSION_RADIUS * 2.,
+                intensity: 1.5,
+                ..default()
+            })
+            .insert(ExplosionFlash(Timer::from_seconds(
+                EXPLOSION_FLASH_TIME,
+                false,
+            )))
+            .insert(Name::new("Explosion"));
+
+        if !network_role.is_authority() {
+            continue;
+        }
+
+        // The closer to the barrel the harder it hits
+        let falloff = |position: Vec2| {
+            let distance = position.distance(event.position);
+
+            (distance < EXPLOSION_RADIUS).then(|| {
+                (
+                    1. - distance / EXPLOSION_RADIUS,
+                    (position - event.position).normalize_or_zero(),
+                )
+            })
+        };
+
+        // Other barrels in the blast go off too, which sets off chain reactions
+        for (entity, transform) in targets.iter() {
+            let (strength, direction) = match falloff(transform.translation.xy()) {
+                Some(falloff) => falloff,
+                None => continue,
+            };
+
+            entity_hit_events.send(EntityHitEvent {
+                entity,
+                amount: EXPLOSION_DAMAGE * strength,
+                source: event.source,
+                point: event.position,
+            });
+
+            if let Ok(mut movement) = enemies.get_mut(entity) {
+                movement.knock_back(direction * EXPLOSION_KNOCKBACK * strength);
+            }
+        }
+
+        for (transform, mut health, dash, mut movement) in players.iter_mut() {
+            if dash.is_invulnerable() {
+                continue;
+            }
+
+            let (strength, direction) = match falloff(transform.translation.xy()) {
+                Some(falloff) => falloff,
+                None => continue,
+            };
+
+            health.take_damage(EXPLOSION_DAMAGE * strength);
+            player_damaged_events.send(PlayerDamagedEvent {
+                amount: EXPLOSION_DAMAGE * strength,
+            });
+
+            movement.knock_back(direction * EXPLOSION_KNOCKBACK * strength);
+        }
+    }
+}
+
+fn fade_explosions(
+    mut commands: Commands,
+    mut explosions: Query<(Entity, &mut Sprite, &mut PointLight2d, &mut ExplosionFlash)>,
+    time: Res<Time>,
+) {
+    for (entity, mut sprite, mut light, mut flash) in explosions.iter_mut() {
+        if flash.tick(time.delta()).finished() {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+
+        sprite.color.set_a(0.6 * (1. - flash.percent()));
+        light.intensity = 1.5 * (1. - flash.percent());
+    }
+}
+
+fn drop_explosions(mut commands: Commands, explosions: Query<Entity, With<ExplosionFlash>>) {
+    for explosion in explosions.iter() {
+        commands.entity(explosion).despawn_recursive();
+    }
+}
+
+fn electrify_tiles(
+    mut tiles: Query<(
+        Entity,
+        &Transform,
+        &ElectrifiedTile,
+        &mut Sprite,
+        &mut PointLight2d,
+    )>,
+    enemies: Query<(Entity, &Transform), With<Enemy>>,
+    mut players: Query<(&Transform, &mut Health, &Dash), With<Player>>,
+    mut entity_hit_events: EventWriter<EntityHitEvent>,
+    mut player_damaged_events: EventWriter<PlayerDamagedEvent>,
+    time: Res<Time>,
+    clock: Res<ArenaClock>,
+    network_role: Res<NetworkRole>,
+) {
+    for (tile_entity, transform, tile, mut sprite, mut light) in tiles.iter_mut() {
+        let on = tile.is_on(&clock);
+
+        // Flickers while on
+        let flicker = (time.seconds_since_startup() as f32 * 40.).sin() * 0.1;
+        sprite.color.set_a(if on { 0.5 + flicker } else { 0.1 });
+        light.intensity = if on { 0.8 + flicker } else { 0. };
+
+        if !on || !network_role.is_authority() {
+            continue;
+        }
+
+        let position = transform.translation.xy();
+        let amount = ELECTRIFIED_DAMAGE_PER_SECOND * time.delta_seconds();
+
+        let on_tile = |other: &Transform| {
+            let offset = (other.translation.xy() - position).abs();
+
+            offset.max_element() < ELECTRIFIED_TILE_SIZE / 2.
+        };
+
+        for (enemy, enemy_transform) in enemies.iter() {
+            if on_tile(enemy_transform) {
+                entity_hit_events.send(EntityHitEvent {
+                    entity: enemy,
+                    amount,
+                    source: DamageSource {
+                        kind: DamageKind::Shock,
+                        entity: tile_entity,
+                    },
+                    point: enemy_transform.translation.xy(),
+                });
+            }
+        }
+
+        for (player_transform, mut health, dash) in players.iter_mut() {
+            if on_tile(player_transform) && !dash.is_invulnerable() {
+                health.take_damage(amount);
+                player_damaged_events.send(PlayerDamagedEvent { amount });
+            }
+        }
+    }
+}
@@ -0,0 +1,168 @@
+use std::f32::consts::PI;
+
+use bevy::{
+    math::Vec3Swizzles,
+    prelude::*,
+    render::render_resource::{Extent3d, TextureDimension, TextureFormat},
+};
+
+use crate::{
+    camera::{screen_size, MainCamera},
+    enemy::Enemy,
+    GameState,
+};
+
+pub struct IndicatorPlugin;
+
+/// This plugin shows arrows on the edge of the screen pointing at the enemies outside of the view
+impl Plugin for IndicatorPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_startup_system(indicator_setup)
+            .add_system_set(
+                SystemSet::on_update(GameState::Playing)
+                    .with_system(spawn_indicators)
+                    .with_system(update_indicators),
+            )
+            .add_system_set(SystemSet::on_exit(GameState::Playing).with_system(drop_indicators));
+    }
+}
+
+/// Distance from the edge of the screen to the indicators
+pub const INDICATOR_MARGIN: f32 = 24.;
+/// Size of the indicator of an enemy right outside of the screen
+pub const INDICATOR_SIZE_MAX: f32 = 32.;
+/// Size of the indicator of an enemy at [INDICATOR_FALLOFF_DISTANCE] or further
+pub const INDICATOR_SIZE_MIN: f32 = 12.;
+pub const INDICATOR_FALLOFF_DISTANCE: f32 = 600.;
+
+/// Points at the given enemy while it's off-screen
+#[derive(Component)]
+struct OffscreenIndicator(Entity);
+
+/// A white arrow pointing up
+#[derive(Deref, DerefMut)]
+pub struct ArrowTexture(pub Handle<Image>);
+
+const ARROW_TEXTURE_SIZE: u32 = 32;
+
+fn indicator_setup(mut commands: Commands, mut images: ResMut<Assets<Image>>) {
+    let size = ARROW_TEXTURE_SIZE;
+    let mut data = Vec::with_capacity((size * size * 4) as usize);
+
+    // A triangle pointing up
+    for y in 0..size {
+        let half_width = y as f32 / size as f32 / 2.;
+
+        for x in 0..size {
+            let inside = (x as f32 / size as f32 - 0.5).abs() <= half_width;
+
+            data.extend_from_slice(&[255, 255, 255, if inside { 255 } else { 0 }]);
+        }
+    }
+
+    let image = Image::new(
+        Extent3d {
+            width: size,
+            height: size,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+    );
+
+    commands.insert_resource(ArrowTexture(images.add(image)));
+}
+
+fn spawn_indicators(
+    mut commands: Commands,
+    enemies: Query<Entity, Added<Enemy>>,
+    texture: Res<ArrowTexture>,
+) {
+    for enemy in enemies.iter() {
+        commands
+            .spawn_bundle(ImageBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    ..default()
+                },
+                image: texture.0.clone().into(),
+                color: Color::rgb(1., 0.3, 0.3).into(),
+                visibility: Visibility { is_visible: false },
+                ..default()
+            })
+            .insert(Name::new("OffscreenIndicator"))
+            .insert(OffscreenIndicator(enemy));
+    }
+}
+
+fn update_indicators(
+    mut commands: Commands,
+    mut indicators: Query<(
+        Entity,
+        &OffscreenIndicator,
+        &mut Style,
+        &mut Transform,
+        &mut Visibility,
+    )>,
+    enemies: Query<&Transform, (With<Enemy>, Without<OffscreenIndicator>)>,
+    camera: Query<
+        (&Transform, &OrthographicProjection),
+        (With<MainCamera>, Without<OffscreenIndicator>),
+    >,
+    windows: Res<Windows>,
+) {
+    let half_screen = screen_size(&windows) / 2.;
+    let (camera_transform, projection) = camera.single();
+    let camera_position = camera_transform.translation.xy();
+
+    for (entity, indicator, mut style, mut transform, mut visibility) in indicators.iter_mut() {
+        let enemy_transform = match enemies.get(indicator.0) {
+            Ok(enemy_transform) => enemy_transform,
+            Err(_) => {
+                commands.entity(entity).despawn_recursive();
+                continue;
+            }
+        };
+
+        // In screen pixels, the camera zooms out in co-op
+        let to_enemy = (enemy_transform.translation.xy() - camera_position) / projection.scale;
+
+        visibility.is_visible =
+            to_enemy.x.abs() > half_screen.x || to_enemy.y.abs() > half_screen.y;
+
+        if !visibility.is_visible {
+            continue;
+        }
+
+        // Where the line from the center of the screen to the enemy crosses the edge of the screen
+        let bounds = half_screen - Vec2::splat(INDICATOR_MARGIN);
+        let edge_position = to_enemy
+            * (bounds.x / to_enemy.x.abs())
+                .min(bounds.y / to_enemy.y.abs())
+                .min(1.);
+
+        let distance_to_screen = (to_enemy - edge_position).length();
+        let size = INDICATOR_SIZE_MAX
+            - (INDICATOR_SIZE_MAX - INDICATOR_SIZE_MIN)
+                * (distance_to_screen / INDICATOR_FALLOFF_DISTANCE).min(1.);
+
+        let screen_position = half_screen + edge_position - Vec2::splat(size / 2.);
+
+        style.size = Size::new(Val::Px(size), Val::Px(size));
+        style.position = UiRect {
+            left: Val::Px(screen_position.x),
+            bottom: Val::Px(screen_position.y),
+            ..default()
+        };
+
+        // The arrow texture points up
+        transform.rotation = Quat::from_rotation_z(to_enemy.y.atan2(to_enemy.x) - PI / 2.);
+    }
+}
+
+fn drop_indicators(mut commands: Commands, indicators: Query<Entity, With<OffscreenIndicator>>) {
+    for indicator in indicators.iter() {
+        commands.entity(indicator).despawn_recursive();
+    }
+}
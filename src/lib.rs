@@ -1,25 +1,46 @@
+mod achievements;
 mod actions;
 mod audio;
+mod barricade;
+mod boss;
 mod camera;
 mod character;
 mod debug;
 mod enemy;
 mod game_area;
 mod game_over;
+mod hazards;
+mod indicators;
+mod lighting;
 mod loading;
 mod menu;
+mod mirror;
+mod network;
+mod pause;
+mod perks;
 mod player;
+mod progression;
+mod projectile;
 mod score;
+mod settings;
 mod shaders;
+mod stats;
+mod status;
+mod storage;
 mod ui;
 mod weapon;
+mod world_ui;
 
+use crate::achievements::AchievementsPlugin;
 use crate::actions::ActionsPlugin;
 use crate::audio::InternalAudioPlugin;
+use crate::barricade::BarricadePlugin;
+use crate::boss::BossPlugin;
 use crate::enemy::EnemyPlugin;
 use crate::game_area::GameAreaPlugin;
 use crate::loading::LoadingPlugin;
 use crate::menu::MenuPlugin;
+use crate::mirror::MirrorPlugin;
 use crate::player::PlayerPlugin;
 use crate::ui::HealthBarPlugin;
 use bevy::app::App;
@@ -32,9 +53,23 @@ use camera::CameraPlugin;
 use character::HealthPlugin;
 use debug::DebugPlugin;
 use game_over::GameOver;
+use hazards::HazardPlugin;
+use indicators::IndicatorPlugin;
+use lighting::LightingPlugin;
+use network::NetworkPlugin;
+use pause::PausePlugin;
+use perks::PerkPlugin;
+use progression::ProgressionPlugin;
+use projectile::ProjectilePlugin;
 use score::ScorePlugin;
+use settings::SettingsPlugin;
 use shaders::ShaderPlugin;
+use stats::StatsPlugin;
+use status::StatusPlugin;
 use weapon::WeaponPlugin;
+use world_ui::WorldUiPlugin;
+
+pub use network::NetworkRole;
 
 pub const GAME_AREA_WIDTH: f32 = 1000.0;
 pub const GAME_AREA_HEIGHT: f32 = 800.0;
@@ -55,6 +90,14 @@ pub enum GameState {
     Menu,
     // Game over screen ¯\_(ツ)_/¯
     GameOver,
+    // Gallery of the achievements, reachable from the menu
+    Achievements,
+    // Pushed on top of Playing to pick a perk, see the PerkPlugin
+    PerkDraft,
+    // Pushed on top of Playing while the game is paused
+    Paused,
+    // Spending the credits earned in earlier runs, reachable from the menu
+    Upgrades,
 }
 
 pub struct GamePlugin;
@@ -64,20 +107,36 @@ impl Plugin for GamePlugin {
         app.add_state(GameState::Loading)
             .add_plugin(RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(100.0))
             .add_plugin(DebugPlugin)
+            .add_plugin(SettingsPlugin)
             .add_plugin(LoadingPlugin)
             .add_plugin(MenuPlugin)
             .add_plugin(GameOver)
             .add_plugin(ShaderPlugin)
             .add_plugin(ActionsPlugin)
+            .add_plugin(NetworkPlugin)
             .add_plugin(InternalAudioPlugin)
             .add_plugin(CameraPlugin)
+            .add_plugin(LightingPlugin)
             .add_plugin(GameAreaPlugin)
+            .add_plugin(HazardPlugin)
             .add_plugin(PlayerPlugin)
             .add_plugin(HealthPlugin)
             .add_plugin(ScorePlugin)
+            .add_plugin(StatsPlugin)
+            .add_plugin(AchievementsPlugin)
             .add_plugin(WeaponPlugin)
+            .add_plugin(MirrorPlugin)
             .add_plugin(HealthBarPlugin)
-            .add_plugin(EnemyPlugin);
+            .add_plugin(IndicatorPlugin)
+            .add_plugin(WorldUiPlugin)
+            .add_plugin(EnemyPlugin)
+            .add_plugin(BossPlugin)
+            .add_plugin(BarricadePlugin)
+            .add_plugin(ProjectilePlugin)
+            .add_plugin(StatusPlugin)
+            .add_plugin(PerkPlugin)
+            .add_plugin(ProgressionPlugin)
+            .add_plugin(PausePlugin);
 
         #[cfg(debug_assertions)]
         {
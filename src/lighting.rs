@@ -0,0 +1,416 @@
+use bevy::{
+    core_pipeline::clear_color::ClearColorConfig,
+    prelude::*,
+    render::{
+        render_resource::{Extent3d, TextureDimension, TextureFormat},
+        view::VisibilitySystems,
+    },
+    sprite::MaterialMesh2dBundle,
+    transform::TransformSystem,
+};
+use bevy_rapier2d::prelude::Collider;
+
+use crate::{
+    camera::{LightCamera, LightingLayer, MainCamera},
+    shaders::shadowed_light::{ShadowedLightMaterial, MAX_SHADOW_EDGES},
+};
+
+pub struct LightingPlugin;
+
+/// This plugin draws the lights into the light map, which is then multiplied
+/// with the image of the main camera in the post processing pass.
+/// Everything that is not lit is drawn in the [AmbientLight2d] color.
+impl Plugin for LightingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<AmbientLight2d>()
+            .add_startup_system(lighting_setup)
+            .add_system(update_ambient_light)
+            .add_system(spawn_light_sprites)
+            .add_system_set_to_stage(
+                CoreStage::PostUpdate,
+                SystemSet::new()
+                    .after(TransformSystem::TransformPropagate)
+                    .before(VisibilitySystems::UpdateOrthographicFrusta)
+                    .before(VisibilitySystems::CheckVisibility)
+                    .with_system(sync_light_camera)
+                    .with_system(sync_lights.label("sync_lights"))
+                    .with_system(cast_shadows.after("sync_lights")),
+            );
+    }
+}
+
+/// The color of the unlit parts of the screen
+#[derive(Deref, DerefMut)]
+pub struct AmbientLight2d(pub Color);
+
+impl Default for AmbientLight2d {
+    fn default() -> Self {
+        Self(Color::rgb(0.18, 0.17, 0.22))
+    }
+}
+
+/// A light shining in all directions from the position of the entity
+#[derive(Component, Clone)]
+pub struct PointLight2d {
+    pub color: Color,
+    pub radius: f32,
+    pub intensity: f32,
+    /// Whether entities with a [LightOccluder] block this light
+    pub casts_shadows: bool,
+}
+
+impl Default for PointLight2d {
+    fn default() -> Self {
+        Self {
+            color: Color::WHITE,
+            radius: 200.,
+            intensity: 1.,
+            casts_shadows: false,
+        }
+    }
+}
+
+/// A light shining along a line segment in world space, like the laser beam
+#[derive(Component, Clone)]
+pub struct LineLight2d {
+    pub color: Color,
+    pub start: Vec2,
+    pub end: Vec2,
+    pub width: f32,
+    pub intensity: f32,
+}
+
+impl Default for LineLight2d {
+    fn default() -> Self {
+        Self {
+            color: Color::WHITE,
+            start: Vec2::ZERO,
+            end: Vec2::ZERO,
+            width: 60.,
+            intensity: 1.,
+        }
+    }
+}
+
+/// Marks an entity whose cuboid [Collider] casts shadows
+#[derive(Component)]
+pub struct LightOccluder;
+
+/// The sprite drawn into the light map for the light on the given entity
+#[derive(Component)]
+struct LightSprite(Entity);
+
+/// The quad drawn into the light map for the shadow casting point light on
+/// the given entity, its shadows are cut out of it by [ShadowedLightMaterial]
+#[derive(Component)]
+struct ShadowedLight(Entity);
+
+struct LightTextures {
+    point: Handle<Image>,
+    line: Handle<Image>,
+}
+
+const LIGHT_TEXTURE_SIZE: u32 = 64;
+
+fn lighting_setup(mut commands: Commands, mut images: ResMut<Assets<Image>>) {
+    let size = LIGHT_TEXTURE_SIZE as f32;
+    let center = Vec2::splat((size - 1.) / 2.);
+
+    // Light falling off from the center
+    let point = light_texture(LIGHT_TEXTURE_SIZE, LIGHT_TEXTURE_SIZE, |x, y| {
+        (Vec2::new(x as f32, y as f32) - center).length() / (size / 2.)
+    });
+
+    // Light falling off from the middle row
+    let line = light_texture(1, LIGHT_TEXTURE_SIZE, |_, y| {
+        (y as f32 - center.y).abs() / (size / 2.)
+    });
+
+    commands.insert_resource(LightTextures {
+        point: images.add(point),
+        line: images.add(line),
+    });
+}
+
+/// Creates a white texture whose alpha falls off quadratically with the
+/// distance returned by `distance` (0 being the brightest, 1 being dark)
+fn light_texture(width: u32, height: u32, distance: impl Fn(u32, u32) -> f32) -> Image {
+    let mut data = Vec::with_capacity((width * height * 4) as usize);
+
+    for y in 0..height {
+        for x in 0..width {
+            let alpha = (1. - distance(x, y)).clamp(0., 1.).powi(2);
+
+            data.extend_from_slice(&[255, 255, 255, (alpha * 255.) as u8]);
+        }
+    }
+
+    Image::new(
+        Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+    )
+}
+
+fn update_ambient_light(
+    ambient_light: Res<AmbientLight2d>,
+    mut light_camera: Query<&mut Camera2d, With<LightCamera>>,
+) {
+    if !ambient_light.is_changed() {
+        return;
+    }
+
+    for mut camera in light_camera.iter_mut() {
+        camera.clear_color = ClearColorConfig::Custom(ambient_light.0);
+    }
+}
+
+fn spawn_light_sprites(
+    mut commands: Commands,
+    point_lights: Query<(Entity, &PointLight2d), Added<PointLight2d>>,
+    line_lights: Query<Entity, Added<LineLight2d>>,
+    textures: Res<LightTextures>,
+    lighting_layer: Res<LightingLayer>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ShadowedLightMaterial>>,
+) {
+    for (owner, light) in point_lights.iter() {
+        if !light.casts_shadows {
+            spawn_light_sprite(&mut commands, owner, &textures.point, &lighting_layer);
+            continue;
+        }
+
+        // A unit quad, scaled to the radius of the light in cast_shadows
+        commands
+            .spawn_bundle(MaterialMesh2dBundle {
+                mesh: meshes.add(Mesh::from(shape::Quad::new(Vec2::ONE))).into(),
+                material: materials.add(ShadowedLightMaterial::default()),
+                visibility: Visibility { is_visible: false },
+                ..default()
+            })
+            .insert(lighting_layer.0)
+            .insert(Name::new("Light"))
+            .insert(ShadowedLight(owner));
+    }
+
+    for owner in line_lights.iter() {
+        spawn_light_sprite(&mut commands, owner, &textures.line, &lighting_layer);
+    }
+}
+
+fn spawn_light_sprite(
+    commands: &mut Commands,
+    owner: Entity,
+    texture: &Handle<Image>,
+    lighting_layer: &LightingLayer,
+) {
+    commands
+        .spawn_bundle(SpriteBundle {
+            texture: texture.clone(),
+            visibility: Visibility { is_visible: false },
+            ..default()
+        })
+        .insert(lighting_layer.0)
+        .insert(Name::new("Light"))
+        .insert(LightSprite(owner));
+}
+
+/// Every light gets its own depth so that overlapping lights are always drawn in the same order
+fn light_depth(owner: Entity) -> f32 {
+    (owner.id() % 800) as f32
+}
+
+fn sync_light_camera(
+    main_camera: Query<
+        (&Transform, &GlobalTransform, &OrthographicProjection),
+        (With<MainCamera>, Without<LightCamera>),
+    >,
+    mut light_camera: Query<
+        (
+            &mut Transform,
+            &mut GlobalTransform,
+            &mut OrthographicProjection,
+        ),
+        With<LightCamera>,
+    >,
+) {
+    let (main_transform, main_global_transform, main_projection) = main_camera.single();
+
+    for (mut transform, mut global_transform, mut projection) in light_camera.iter_mut() {
+        *transform = *main_transform;
+        *global_transform = *main_global_transform;
+
+        // Follow the zoom of the main camera
+        if projection.scale != main_projection.scale {
+            projection.scale = main_projection.scale;
+        }
+    }
+}
+
+fn sync_lights(
+    mut commands: Commands,
+    mut light_sprites: Query<(
+        Entity,
+        &LightSprite,
+        &mut Transform,
+        &mut GlobalTransform,
+        &mut Sprite,
+        &mut Visibility,
+    )>,
+    owners: Query<
+        (
+            &GlobalTransform,
+            Option<&Visibility>,
+            Option<&PointLight2d>,
+            Option<&LineLight2d>,
+        ),
+        Without<LightSprite>,
+    >,
+) {
+    for (entity, light_sprite, mut transform, mut global_transform, mut sprite, mut visibility) in
+        light_sprites.iter_mut()
+    {
+        let (owner_transform, owner_visibility, point_light, line_light) =
+            match owners.get(light_sprite.0) {
+                Ok(owner) => owner,
+                Err(_) => {
+                    commands.entity(entity).despawn_recursive();
+                    continue;
+                }
+            };
+
+        visibility.is_visible = owner_visibility.map_or(true, |visibility| visibility.is_visible);
+
+        let depth = light_depth(light_sprite.0);
+
+        if let Some(light) = point_light {
+            *transform =
+                Transform::from_translation(owner_transform.translation().truncate().extend(depth));
+
+            sprite.custom_size = Some(Vec2::splat(light.radius * 2.));
+            sprite.color = light_color(light.color, light.intensity);
+        } else if let Some(light) = line_light {
+            let beam = light.end - light.start;
+
+            *transform = Transform {
+                translation: ((light.start + light.end) / 2.).extend(depth),
+                rotation: Quat::from_rotation_z(beam.y.atan2(beam.x)),
+                ..default()
+            };
+
+            sprite.custom_size = Some(Vec2::new(beam.length() + light.width, light.width));
+            sprite.color = light_color(light.color, light.intensity);
+        } else {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+
+        // Transforms have already been propagated this frame
+        *global_transform = GlobalTransform::from(*transform);
+    }
+}
+
+fn light_color(mut color: Color, intensity: f32) -> Color {
+    color.set_a(intensity.clamp(0., 1.));
+    color
+}
+
+fn cast_shadows(
+    mut commands: Commands,
+    mut shadowed_lights: Query<(
+        Entity,
+        &ShadowedLight,
+        &Handle<ShadowedLightMaterial>,
+        &mut Transform,
+        &mut GlobalTransform,
+        &mut Visibility,
+    )>,
+    lights: Query<(&GlobalTransform, &PointLight2d, Option<&Visibility>), Without<ShadowedLight>>,
+    occluders: Query<(&GlobalTransform, &Collider), (With<LightOccluder>, Without<ShadowedLight>)>,
+    mut materials: ResMut<Assets<ShadowedLightMaterial>>,
+) {
+    for (entity, shadowed_light, material, mut transform, mut global_transform, mut visibility) in
+        shadowed_lights.iter_mut()
+    {
+        let (light_transform, light, light_visibility) = match lights.get(shadowed_light.0) {
+            Ok(light) => light,
+            Err(_) => {
+                commands.entity(entity).despawn_recursive();
+                continue;
+            }
+        };
+
+        visibility.is_visible = light_visibility.map_or(true, |visibility| visibility.is_visible);
+
+        if !visibility.is_visible {
+            continue;
+        }
+
+        let light_position = light_transform.translation().truncate();
+
+        *transform =
+            Transform::from_translation(light_position.extend(light_depth(shadowed_light.0)))
+                .with_scale(Vec3::new(light.radius * 2., light.radius * 2., 1.));
+
+        // Transforms have already been propagated this frame
+        *global_transform = GlobalTransform::from(*transform);
+
+        let mut edges = Vec::new();
+
+        for (occluder_transform, collider) in occluders.iter() {
+            let half_extents = match collider.as_cuboid() {
+                Some(cuboid) => cuboid.half_extents(),
+                None => continue,
+            };
+
+            let matrix = occluder_transform.compute_matrix();
+
+            // Corners in counter-clockwise order
+            let corners = [
+                Vec2::new(-half_extents.x, -half_extents.y),
+                Vec2::new(half_extents.x, -half_extents.y),
+                Vec2::new(half_extents.x, half_extents.y),
+                Vec2::new(-half_extents.x, half_extents.y),
+            ]
+            .map(|corner| matrix.transform_point3(corner.extend(0.)).truncate());
+
+            if corners
+                .iter()
+                .all(|corner| corner.distance(light_position) > light.radius)
+            {
+                continue;
+            }
+
+            for i in 0..corners.len() {
+                let a = corners[i];
+                let b = corners[(i + 1) % corners.len()];
+
+                let edge = b - a;
+                let normal = Vec2::new(edge.y, -edge.x);
+
+                // Only the edges facing away from the light cast shadows, so the occluder itself stays lit
+                if normal.dot((a + b) / 2. - light_position) <= 0. {
+                    continue;
+                }
+
+                edges.push(Vec4::new(a.x, a.y, b.x, b.y));
+            }
+        }
+
+        // The material only has room for so many edges, the walls around a light stay well below that
+        edges.truncate(MAX_SHADOW_EDGES);
+
+        if let Some(material) = materials.get_mut(material) {
+            material.color = light_color(light.color, light.intensity);
+            material.position = light_position;
+            material.radius = light.radius;
+            material.edge_count = edges.len() as u32;
+            material.edges[..edges.len()].copy_from_slice(&edges);
+        }
+    }
+}
@@ -1,16 +1,22 @@
 // disable console on windows for release builds
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-use bevy::prelude::{App, ClearColor, Color, Msaa, NonSend, WindowDescriptor};
+use bevy::app::{ScheduleRunnerPlugin, ScheduleRunnerSettings};
+use bevy::prelude::{default, App, ClearColor, Color, Msaa, NonSend, WindowDescriptor};
+use bevy::render::settings::WgpuSettings;
 use bevy::window::WindowId;
-use bevy::winit::WinitWindows;
+use bevy::winit::{WinitPlugin, WinitWindows};
 use bevy::DefaultPlugins;
-use laser_beam::GamePlugin;
+use laser_beam::{GamePlugin, NetworkRole};
 use std::io::Cursor;
+use std::time::Duration;
 use winit::window::Icon;
 
 pub const TITLE: &str = "LASER BEAM!";
 
+/// Frames per second of a headless server, which has no display to sync to
+pub const SERVER_TICK_RATE: f64 = 60.;
+
 #[cfg(target_arch="wasm32")]
 fn window_size() -> (f32, f32) {
     (931., 516.)
@@ -22,21 +28,35 @@ fn window_size() -> (f32, f32) {
 }
 
 fn main() {
-    let size = window_size();
-
-    App::new()
-        .insert_resource(Msaa { samples: 1 })
-        .insert_resource(ClearColor(Color::rgb(0.1, 0.1, 0.1)))
-        .insert_resource(WindowDescriptor {
-            width: size.0,
-            height: size.1,
-            title: TITLE.to_string(),
-            ..Default::default()
+    let mut app = App::new();
+
+    if NetworkRole::from_args().is_headless() {
+        // No window and no renderer, the game loop runs on its own
+        app.insert_resource(WgpuSettings {
+            backends: None,
+            ..default()
         })
-        .add_plugins(DefaultPlugins)
-        .add_plugin(GamePlugin)
-        .add_startup_system(set_window_icon)
-        .run();
+        .insert_resource(ScheduleRunnerSettings::run_loop(Duration::from_secs_f64(
+            1. / SERVER_TICK_RATE,
+        )))
+        .add_plugins_with(DefaultPlugins, |group| group.disable::<WinitPlugin>())
+        .add_plugin(ScheduleRunnerPlugin);
+    } else {
+        let size = window_size();
+
+        app.insert_resource(Msaa { samples: 1 })
+            .insert_resource(ClearColor(Color::rgb(0.1, 0.1, 0.1)))
+            .insert_resource(WindowDescriptor {
+                width: size.0,
+                height: size.1,
+                title: TITLE.to_string(),
+                ..Default::default()
+            })
+            .add_plugins(DefaultPlugins)
+            .add_startup_system(set_window_icon);
+    }
+
+    app.add_plugin(GamePlugin).run();
 }
 
 // Sets the icon on windows and X11
@@ -1,4 +1,7 @@
 use crate::loading::{FontAssets};
+use crate::network::NetworkRole;
+use crate::player::PlayerCount;
+use crate::settings::Settings;
 use crate::GameState;
 use bevy::prelude::*;
 
@@ -8,6 +11,37 @@ pub struct MenuPlugin;
 #[derive(Component)]
 struct MainMenuUi;
 
+#[derive(Component, Clone, Copy)]
+enum MenuButton {
+    Play,
+    Coop,
+    Achievements,
+    Upgrades,
+    AimAssist,
+}
+
+impl MenuButton {
+    fn label(&self, settings: &Settings) -> String {
+        match self {
+            MenuButton::Play => "Play".to_string(),
+            MenuButton::Coop => "Co-op".to_string(),
+            MenuButton::Achievements => "Achievements".to_string(),
+            MenuButton::Upgrades => "Upgrades".to_string(),
+            MenuButton::AimAssist => format!("Aim assist: {}", settings.aim_assist_name()),
+        }
+    }
+
+    fn width(&self) -> f32 {
+        match self {
+            MenuButton::Play => 120.0,
+            MenuButton::Coop => 160.0,
+            MenuButton::Achievements => 260.0,
+            MenuButton::Upgrades => 200.0,
+            MenuButton::AimAssist => 300.0,
+        }
+    }
+}
+
 
 /// This plugin is responsible for the game menu (containing only one button...)
 /// The menu is only drawn during the State `GameState::Menu` and is removed when that state is exited
@@ -39,6 +73,8 @@ fn setup_menu(
     font_assets: Res<FontAssets>,
     button_colors: Res<ButtonColors>,
     asset_server: Res<AssetServer>,
+    settings: Res<Settings>,
+    network_role: Res<NetworkRole>,
 ) {
     commands
         .spawn_bundle(NodeBundle {
@@ -57,6 +93,9 @@ fn setup_menu(
         .with_children(|parent| {
             parent.spawn_bundle(ImageBundle {
                 style: Style {
+                    // The logo is 1000x600, scaled down so the buttons still fit in small windows
+                    size: Size::new(Val::Auto, Val::Percent(30.0)),
+                    aspect_ratio: Some(1000.0 / 600.0),
                     ..Default::default()
                 },
                 // i know this shouldnt be done this way but ive tried doing it with the assets collection but
@@ -65,48 +104,91 @@ fn setup_menu(
                 ..Default::default()
             });
 
-            parent.spawn_bundle(ButtonBundle {
-                style: Style {
-                    size: Size::new(Val::Px(120.0), Val::Px(50.0)),
-                    margin: UiRect::all(Val::Auto),
-                    justify_content: JustifyContent::Center,
-                    align_items: AlignItems::Center,
-                    ..Default::default()
-                },
-                color: button_colors.normal,
-                ..Default::default()
-            })
-            .with_children(|parent| {
-                parent.spawn_bundle(TextBundle {
-                    text: Text {
-                        sections: vec![TextSection {
-                            value: "Play".to_string(),
-                            style: TextStyle {
-                                font: font_assets.fira_sans.clone(),
-                                font_size: 40.0,
-                                color: Color::rgb(0.9, 0.9, 0.9),
-                            },
-                        }],
-                        alignment: Default::default(),
+            for button in [
+                MenuButton::Play,
+                MenuButton::Coop,
+                MenuButton::Achievements,
+                MenuButton::Upgrades,
+                MenuButton::AimAssist,
+            ] {
+                // The host only gives a joining game one player
+                if matches!(button, MenuButton::Coop) && matches!(*network_role, NetworkRole::Client(_)) {
+                    continue;
+                }
+
+                parent.spawn_bundle(ButtonBundle {
+                    style: Style {
+                        size: Size::new(Val::Px(button.width()), Val::Px(50.0)),
+                        margin: UiRect::new(Val::Auto, Val::Auto, Val::Px(8.0), Val::Px(8.0)),
+                        justify_content: JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        ..Default::default()
                     },
+                    color: button_colors.normal,
                     ..Default::default()
-                });
-            });
+                })
+                .with_children(|parent| {
+                    parent.spawn_bundle(TextBundle {
+                        text: Text {
+                            sections: vec![TextSection {
+                                value: button.label(&settings),
+                                style: TextStyle {
+                                    font: font_assets.fira_sans.clone(),
+                                    font_size: 40.0,
+                                    color: Color::rgb(0.9, 0.9, 0.9),
+                                },
+                            }],
+                            alignment: Default::default(),
+                        },
+                        ..Default::default()
+                    });
+                })
+                .insert(button);
+            }
         });
 }
 
 fn click_play_button(
     button_colors: Res<ButtonColors>,
     mut state: ResMut<State<GameState>>,
+    mut player_count: ResMut<PlayerCount>,
+    mut settings: ResMut<Settings>,
     mut interaction_query: Query<
-        (&Interaction, &mut UiColor),
+        (&Interaction, &mut UiColor, &MenuButton, &Children),
         (Changed<Interaction>, With<Button>),
     >,
+    mut text_query: Query<&mut Text>,
 ) {
-    for (interaction, mut color) in &mut interaction_query {
+    for (interaction, mut color, button, children) in &mut interaction_query {
         match *interaction {
             Interaction::Clicked => {
-                state.set(GameState::Playing).unwrap();
+                let next_state = match button {
+                    MenuButton::Play => {
+                        player_count.0 = 1;
+                        GameState::Playing
+                    }
+                    // The second player uses a gamepad
+                    MenuButton::Coop => {
+                        player_count.0 = 2;
+                        GameState::Playing
+                    }
+                    MenuButton::Achievements => GameState::Achievements,
+                    MenuButton::Upgrades => GameState::Upgrades,
+                    // Stays in the menu, only the label changes
+                    MenuButton::AimAssist => {
+                        settings.cycle_aim_assist();
+
+                        for child in children.iter() {
+                            if let Ok(mut text) = text_query.get_mut(*child) {
+                                text.sections[0].value = button.label(&settings);
+                            }
+                        }
+
+                        continue;
+                    }
+                };
+
+                state.set(next_state).unwrap();
             }
             Interaction::Hovered => {
                 *color = button_colors.hovered;
@@ -0,0 +1,188 @@
+use crate::{
+    actions::PlayerActions, enemy::Enemy, network::NetworkRole, player::Player, GameState,
+};
+use bevy::{math::Vec3Swizzles, prelude::*};
+use bevy_rapier2d::prelude::*;
+
+pub struct MirrorPlugin;
+
+/// This plugin lets players put down mirrors that reflect the beam, see [crate::weapon].
+/// Standing next to a mirror and pressing the button again picks it back up for the player who put it down,
+/// enemies running into one knock it over so it stops reflecting until picked up.
+impl Plugin for MirrorPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::on_update(GameState::Playing)
+                .with_system(equip_mirrors)
+                .with_system(place_mirrors.after("player_actions"))
+                .with_system(knock_over_mirrors),
+        )
+        .add_system_set(SystemSet::on_exit(GameState::Playing).with_system(drop_mirrors));
+    }
+}
+
+/// Mirrors each player starts with
+pub const MIRRORS_CARRIED: usize = 2;
+pub const MIRROR_LENGTH: f32 = 50.;
+pub const MIRROR_THICKNESS: f32 = 6.;
+/// How far in front of the player mirrors are put down
+pub const MIRROR_PLACE_DISTANCE: f32 = 40.;
+/// How close the player has to be to a mirror to pick it up
+pub const MIRROR_PICKUP_RANGE: f32 = 50.;
+/// How close an enemy has to get to a mirror to knock it over
+pub const MIRROR_KNOCK_RANGE: f32 = 30.;
+
+#[derive(Component)]
+pub struct Mirror {
+    pub knocked_over: bool,
+}
+
+impl Mirror {
+    pub fn reflects(&self) -> bool {
+        !self.knocked_over
+    }
+}
+
+/// The player the mirror goes back to when it is picked up, only known to the authority
+#[derive(Component, Deref)]
+pub struct MirrorOwner(pub Entity);
+
+/// The mirrors the player can still put down
+#[derive(Component, Deref, DerefMut)]
+pub struct CarriedMirrors(pub usize);
+
+fn equip_mirrors(mut commands: Commands, players: Query<Entity, Added<Player>>) {
+    for player in players.iter() {
+        commands
+            .entity(player)
+            .insert(CarriedMirrors(MIRRORS_CARRIED));
+    }
+}
+
+pub fn spawn_mirror(commands: &mut Commands, transform: Transform) -> Entity {
+    commands
+        .spawn_bundle(SpriteBundle {
+            sprite: Sprite {
+                color: Color::rgb(0.8, 0.9, 1.),
+                custom_size: Some(Vec2::new(MIRROR_LENGTH, MIRROR_THICKNESS)),
+                ..default()
+            },
+            transform,
+            ..default()
+        })
+        .insert(Mirror {
+            knocked_over: false,
+        })
+        .insert(Collider::cuboid(MIRROR_LENGTH / 2., MIRROR_THICKNESS / 2.))
+        .insert(Name::new("Mirror"))
+        .id()
+}
+
+/// Lying on the floor the beam goes right over it
+pub fn knock_over(
+    commands: &mut Commands,
+    entity: Entity,
+    mirror: &mut Mirror,
+    sprite: &mut Sprite,
+) {
+    mirror.knocked_over = true;
+    sprite.color = Color::rgba(0.5, 0.55, 0.6, 0.6);
+    commands.entity(entity).remove::<Collider>();
+}
+
+fn place_mirrors(
+    mut commands: Commands,
+    mut players: Query<(Entity, &Transform, &mut PlayerActions), With<Player>>,
+    mut carried_mirrors: Query<&mut CarriedMirrors>,
+    mirrors: Query<(Entity, &Transform, &MirrorOwner), With<Mirror>>,
+    network_role: Res<NetworkRole>,
+) {
+    // Clients send the button to the host instead, see [crate::network]
+    if !network_role.is_authority() {
+        return;
+    }
+
+    // Despawning waits for the end of the frame, so two players can't pick up the same mirror
+    let mut picked_up = Vec::new();
+
+    for (player, transform, mut actions) in players.iter_mut() {
+        if !std::mem::take(&mut actions.mirror) {
+            continue;
+        }
+
+        let position = transform.translation.xy();
+
+        let nearest = mirrors
+            .iter()
+            .filter(|(entity, ..)| !picked_up.contains(entity))
+            .map(|(entity, mirror_transform, owner)| {
+                (
+                    entity,
+                    owner.0,
+                    mirror_transform.translation.xy().distance(position),
+                )
+            })
+            .filter(|(.., distance)| *distance < MIRROR_PICKUP_RANGE)
+            .min_by(|(.., a), (.., b)| a.total_cmp(b));
+
+        // Pick up the mirror next to the player before putting down another one
+        if let Some((mirror, owner, _)) = nearest {
+            commands.entity(mirror).despawn_recursive();
+            picked_up.push(mirror);
+
+            // The owner might have died already
+            if let Ok(mut carried) = carried_mirrors.get_mut(owner) {
+                carried.0 += 1;
+            }
+
+            continue;
+        }
+
+        let mut carried = match carried_mirrors.get_mut(player) {
+            Ok(carried) if carried.0 > 0 => carried,
+            _ => continue,
+        };
+
+        carried.0 -= 1;
+
+        // Facing the player, so shooting it at an angle bounces the beam off to the side
+        let mut mirror_transform = *transform;
+        mirror_transform.translation =
+            (position + transform.up().truncate() * MIRROR_PLACE_DISTANCE).extend(1.5);
+        mirror_transform.scale = Vec3::ONE;
+
+        let mirror = spawn_mirror(&mut commands, mirror_transform);
+        commands.entity(mirror).insert(MirrorOwner(player));
+    }
+}
+
+fn knock_over_mirrors(
+    mut commands: Commands,
+    mut mirrors: Query<(Entity, &Transform, &mut Mirror, &mut Sprite)>,
+    enemies: Query<&Transform, With<Enemy>>,
+    network_role: Res<NetworkRole>,
+) {
+    if !network_role.is_authority() {
+        return;
+    }
+
+    for (entity, transform, mut mirror, mut sprite) in mirrors.iter_mut() {
+        if mirror.knocked_over {
+            continue;
+        }
+
+        let position = transform.translation.xy();
+
+        if enemies.iter().any(|enemy_transform| {
+            enemy_transform.translation.xy().distance(position) < MIRROR_KNOCK_RANGE
+        }) {
+            knock_over(&mut commands, entity, &mut mirror, &mut sprite);
+        }
+    }
+}
+
+fn drop_mirrors(mut commands: Commands, mirrors: Query<Entity, With<Mirror>>) {
+    for mirror in mirrors.iter() {
+        commands.entity(mirror).despawn_recursive();
+    }
+}
@@ -0,0 +1,1270 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    io::ErrorKind,
+    net::{SocketAddr, UdpSocket},
+};
+
+use bevy::{ecs::system::SystemParam, math::Vec3Swizzles, prelude::*};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{
+    actions::{InputDevice, PlayerActions, WeaponSwitch},
+    barricade::Barricade,
+    boss::{spawn_boss, Boss},
+    character::{DamageKind, DamageSource, Health},
+    enemy::{Attack, AttackKind, Enemy, EnemyBundle},
+    game_area::{break_wall, ArenaSeed, DestructibleWall, EnemySpawn},
+    hazards::{ArenaClock, ExplosionEvent, ExplosiveBarrel},
+    loading::TextureAssets,
+    mirror::{knock_over, spawn_mirror, Mirror},
+    perks::{HostPerks, PerkDraft, Perks},
+    player::{spawn_player_with_id, Player, PlayerCount, PlayerId},
+    progression::{Loadout, Progression, Unlocks},
+    projectile::{Projectile, ProjectileBundle},
+    score::Score,
+    status::{
+        spawn_pickup, StatusEffectDefinition, StatusEffectState, StatusEffects, StatusPickup,
+    },
+    weapon::{Weapon, WeaponDefinitions, WeaponStatus},
+    GameState,
+};
+
+pub struct NetworkPlugin;
+
+/// This plugin lets players on other machines join a run over UDP.
+/// The game is started with `--host [port]` on one machine and `--join <address>` on the others.
+/// The host runs the game as usual and sends snapshots of it to the clients,
+/// the clients only predict the movement of their own player and send their input to the host.
+/// `--server [port]` hosts without a window or players of its own, starting a new run whenever one ends.
+impl Plugin for NetworkPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(NetworkRole::from_args())
+            .add_system_set(SystemSet::on_enter(GameState::Menu).with_system(start_server_run))
+            .add_system_set(SystemSet::on_enter(GameState::GameOver).with_system(start_server_run))
+            .add_system_set(SystemSet::on_enter(GameState::Playing).with_system(open_socket))
+            // Keeps going while the host is paused or drafting a perk, so the clients stay connected
+            .add_system_set(
+                SystemSet::on_in_stack_update(GameState::Playing)
+                    .with_system(host_receive)
+                    .with_system(host_send_snapshots)
+                    .with_system(client_receive)
+                    .with_system(client_sync_arena)
+                    // Before the player takes the dash, so it can be sent along
+                    .with_system(
+                        client_send_input
+                            .after("player_actions")
+                            .before("player_movement"),
+                    ),
+            )
+            .add_system_set(SystemSet::on_exit(GameState::Playing).with_system(close_socket));
+    }
+}
+
+pub const DEFAULT_PORT: u16 = 7777;
+/// Seconds between two snapshots sent by the host
+pub const SNAPSHOT_INTERVAL: f32 = 1. / 20.;
+/// Seconds between two input messages sent by a client
+pub const INPUT_INTERVAL: f32 = 1. / 30.;
+/// The predicted position of the player is corrected when it is further than this from the host
+pub const RECONCILE_DISTANCE: f32 = 48.;
+
+const MAX_PACKET_SIZE: usize = 65_507;
+/// Snapshots are split into parts of this many bytes, small enough to not be fragmented on the way
+const SNAPSHOT_PART_SIZE: usize = 1_200;
+/// A snapshot that needs more parts than this is a bug, not something to send
+const MAX_SNAPSHOT_PARTS: usize = 256;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum NetworkRole {
+    Offline,
+    /// Runs the game for everyone, listening on the given port
+    Host(u16),
+    /// Like [NetworkRole::Host], but headless and without local players
+    Server(u16),
+    /// Plays in the game of the host at the given address
+    Client(SocketAddr),
+}
+
+impl NetworkRole {
+    pub fn from_args() -> Self {
+        let args = std::env::args().collect::<Vec<_>>();
+        let value_after = |flag: &str| {
+            args.iter()
+                .position(|arg| arg == flag)
+                .map(|index| args.get(index + 1).cloned())
+        };
+
+        let parse_port = |port: Option<String>| {
+            port.and_then(|port| port.parse().ok())
+                .unwrap_or(DEFAULT_PORT)
+        };
+
+        if let Some(port) = value_after("--server") {
+            return NetworkRole::Server(parse_port(port));
+        }
+
+        if let Some(port) = value_after("--host") {
+            return NetworkRole::Host(parse_port(port));
+        }
+
+        if let Some(address) = value_after("--join") {
+            match address.and_then(|address| address.parse().ok()) {
+                Some(address) => return NetworkRole::Client(address),
+                None => warn!("--join needs the address of the host, like 127.0.0.1:7777"),
+            }
+        }
+
+        NetworkRole::Offline
+    }
+
+    /// Whether this game decides what happens, as opposed to receiving it from the host
+    pub fn is_authority(&self) -> bool {
+        !matches!(self, NetworkRole::Client(_))
+    }
+
+    /// Whether the game runs without a window or renderer
+    pub fn is_headless(&self) -> bool {
+        matches!(self, NetworkRole::Server(_))
+    }
+}
+
+#[derive(Deref)]
+struct NetworkSocket(UdpSocket);
+
+#[derive(Deref, DerefMut)]
+struct NetworkTimer(Timer);
+
+/// Kept between frames so receiving doesn't allocate
+#[derive(Deref, DerefMut)]
+struct ReceiveBuffer(Vec<u8>);
+
+/// The parts of the newest snapshot that arrived so far
+#[derive(Default)]
+struct SnapshotAssembly {
+    sequence: u32,
+    parts: Vec<Option<Vec<u8>>>,
+}
+
+impl SnapshotAssembly {
+    /// Adds a part, giving back the whole snapshot once all of its parts are there
+    fn add(
+        &mut self,
+        sequence: u32,
+        index: usize,
+        count: usize,
+        data: Vec<u8>,
+    ) -> Option<Snapshot> {
+        // Parts of older snapshots are already out of date
+        if sequence < self.sequence {
+            return None;
+        }
+
+        if sequence > self.sequence {
+            self.sequence = sequence;
+            self.parts = vec![None; count];
+        }
+
+        // Once put together the parts are gone, so a part sent twice is ignored
+        *self.parts.get_mut(index)? = Some(data);
+
+        if self.parts.iter().any(Option::is_none) {
+            return None;
+        }
+
+        let data = std::mem::take(&mut self.parts)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .collect::<Vec<_>>();
+
+        match bincode::deserialize(&data) {
+            Ok(snapshot) => Some(snapshot),
+            Err(error) => {
+                warn!("Received an invalid snapshot: {}", error);
+                None
+            }
+        }
+    }
+}
+
+/// Presses that happened since the client last sent its input, so none get lost between two messages
+#[derive(Default)]
+struct UnsentInput {
+    dash: bool,
+    mirror: bool,
+}
+
+/// The players of the clients connected to the host
+#[derive(Default)]
+struct RemotePlayers {
+    players: HashMap<SocketAddr, Entity>,
+    joined: usize,
+}
+
+/// The arena in the latest snapshot, kept until the arena of the host is built on this client
+#[derive(Deref, DerefMut)]
+struct LatestArena(Option<ArenaState>);
+
+/// The entity on the host controlled by this client
+#[derive(Deref, DerefMut)]
+struct LocalPlayer(Option<u64>);
+
+/// Mirrors the entity with these bits on the host
+#[derive(Component)]
+struct Replicated(u64);
+
+#[derive(Serialize, Deserialize)]
+enum ClientMessage {
+    /// Asks for a player on the host, made from the unlocks picked on this machine
+    Join {
+        loadout: Loadout,
+    },
+    Input {
+        movement: Option<[f32; 2]>,
+        aim: Option<[f32; 2]>,
+        weapon: usize,
+        /// Whether the player pressed dash since the last message
+        dash: bool,
+        repair: bool,
+        /// Whether the player pressed the mirror button since the last message
+        mirror: bool,
+    },
+    Leave,
+}
+
+#[derive(Serialize, Deserialize)]
+enum ServerMessage {
+    /// Tells the client which of the players in the snapshots is its own
+    /// and which arena to build, see [ArenaSeed]
+    Welcome { player: u64, arena_seed: u64 },
+    /// One piece of a [Snapshot], see [snapshot_parts]
+    SnapshotPart {
+        sequence: u32,
+        index: u16,
+        count: u16,
+        data: Vec<u8>,
+    },
+}
+
+#[derive(Serialize, Deserialize)]
+struct Snapshot {
+    score: u32,
+    players: Vec<PlayerState>,
+    enemies: Vec<EnemyState>,
+    projectiles: Vec<ProjectileState>,
+    mirrors: Vec<MirrorState>,
+    pickups: Vec<PickupState>,
+    arena: ArenaState,
+    perks: HostPerks,
+}
+
+#[derive(Serialize, Deserialize)]
+struct PlayerState {
+    entity: u64,
+    id: usize,
+    position: [f32; 2],
+    rotation: f32,
+    health: f32,
+    beaming: bool,
+    weapon: usize,
+    status_effects: Vec<StatusEffectState>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct EnemyState {
+    entity: u64,
+    position: [f32; 2],
+    rotation: f32,
+    health: f32,
+    ranged: bool,
+    boss: bool,
+    status_effects: Vec<StatusEffectState>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ProjectileState {
+    entity: u64,
+    position: [f32; 2],
+    velocity: [f32; 2],
+    damage: f32,
+}
+
+#[derive(Serialize, Deserialize)]
+struct PickupState {
+    entity: u64,
+    position: [f32; 2],
+    effect: StatusEffectDefinition,
+}
+
+#[derive(Serialize, Deserialize)]
+struct MirrorState {
+    entity: u64,
+    position: [f32; 2],
+    rotation: f32,
+    knocked_over: bool,
+}
+
+/// The parts of the arena that change during a run, found by their place in the spawn order
+#[derive(Serialize, Deserialize)]
+struct ArenaState {
+    /// The arena the state belongs to, see [ArenaSeed]
+    seed: u64,
+    /// See [ArenaClock]
+    clock: f32,
+    /// Indices of the barrels that haven't blown up yet, see [ExplosiveBarrel]
+    barrels: Vec<usize>,
+    /// The walls that haven't been broken yet
+    walls: Vec<WallState>,
+    barricades: Vec<BarricadeState>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct WallState {
+    /// See [DestructibleWall::index]
+    index: usize,
+    heat: f32,
+    health: f32,
+}
+
+#[derive(Serialize, Deserialize)]
+struct BarricadeState {
+    /// See [EnemySpawn::index]
+    window: usize,
+    boards: u32,
+}
+
+/// What the host reads to make the [ArenaState]
+#[derive(SystemParam)]
+struct ArenaParams<'w, 's> {
+    seed: Res<'w, ArenaSeed>,
+    clock: Res<'w, ArenaClock>,
+    barrels: Query<'w, 's, &'static ExplosiveBarrel>,
+    walls: Query<'w, 's, (&'static DestructibleWall, &'static Health)>,
+    barricades: Query<'w, 's, (&'static EnemySpawn, &'static Health), With<Barricade>>,
+}
+
+impl ArenaParams<'_, '_> {
+    fn state(&self) -> ArenaState {
+        ArenaState {
+            seed: self.seed.0,
+            clock: self.clock.0,
+            barrels: self.barrels.iter().map(|barrel| barrel.index).collect(),
+            walls: self
+                .walls
+                .iter()
+                .map(|(wall, health)| WallState {
+                    index: wall.index,
+                    heat: wall.heat,
+                    health: health.get_health(),
+                })
+                .collect(),
+            barricades: self
+                .barricades
+                .iter()
+                .map(|(window, health)| BarricadeState {
+                    window: window.index,
+                    boards: Barricade::boards(health),
+                })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug)]
+enum SnapshotError {
+    Serialize(bincode::Error),
+    /// Needs this many parts, more than [MAX_SNAPSHOT_PARTS]
+    TooLarge(usize),
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SnapshotError::Serialize(error) => write!(f, "failed to serialize it: {}", error),
+            SnapshotError::TooLarge(parts) => write!(
+                f,
+                "it needs {} parts, more than the {} allowed",
+                parts, MAX_SNAPSHOT_PARTS
+            ),
+        }
+    }
+}
+
+/// Splits the snapshot into messages that each fit in a datagram
+fn snapshot_parts(sequence: u32, snapshot: &Snapshot) -> Result<Vec<ServerMessage>, SnapshotError> {
+    let data = bincode::serialize(snapshot).map_err(SnapshotError::Serialize)?;
+    let chunks = data.chunks(SNAPSHOT_PART_SIZE).collect::<Vec<_>>();
+
+    if chunks.len() > MAX_SNAPSHOT_PARTS {
+        return Err(SnapshotError::TooLarge(chunks.len()));
+    }
+
+    Ok(chunks
+        .iter()
+        .enumerate()
+        .map(|(index, chunk)| ServerMessage::SnapshotPart {
+            sequence,
+            index: index as u16,
+            count: chunks.len() as u16,
+            data: chunk.to_vec(),
+        })
+        .collect())
+}
+
+fn send<T: Serialize>(socket: &UdpSocket, address: SocketAddr, message: &T) {
+    match bincode::serialize(message) {
+        Ok(data) => {
+            if let Err(error) = socket.send_to(&data, address) {
+                warn!("Failed to send a message to {}: {}", address, error);
+            }
+        }
+        Err(error) => warn!("Failed to serialize a network message: {}", error),
+    }
+}
+
+/// Everything that arrived since the last frame
+fn receive<T: DeserializeOwned>(socket: &UdpSocket, buffer: &mut [u8]) -> Vec<(SocketAddr, T)> {
+    let mut messages = Vec::new();
+
+    loop {
+        match socket.recv_from(buffer) {
+            Ok((size, address)) => match bincode::deserialize(&buffer[..size]) {
+                Ok(message) => messages.push((address, message)),
+                Err(error) => warn!("Received an invalid message from {}: {}", address, error),
+            },
+            Err(error) if error.kind() == ErrorKind::WouldBlock => break,
+            Err(error) => {
+                warn!("Failed to receive network messages: {}", error);
+                break;
+            }
+        }
+    }
+
+    messages
+}
+
+fn rotation_z(transform: &Transform) -> f32 {
+    transform.rotation.to_euler(EulerRot::XYZ).2
+}
+
+/// Skips the menu and the game over screen on a headless server, nobody is there to click them
+fn start_server_run(
+    mut state: ResMut<State<GameState>>,
+    mut player_count: ResMut<PlayerCount>,
+    network_role: Res<NetworkRole>,
+) {
+    if !network_role.is_headless() {
+        return;
+    }
+
+    // Everyone plays from a client
+    player_count.0 = 0;
+
+    state.set(GameState::Playing).unwrap();
+}
+
+fn open_socket(mut commands: Commands, network_role: Res<NetworkRole>) {
+    let address = match *network_role {
+        NetworkRole::Offline => return,
+        NetworkRole::Host(port) | NetworkRole::Server(port) => {
+            SocketAddr::from(([0, 0, 0, 0], port))
+        }
+        NetworkRole::Client(_) => SocketAddr::from(([0, 0, 0, 0], 0)),
+    };
+
+    match UdpSocket::bind(address).and_then(|socket| socket.set_nonblocking(true).map(|_| socket)) {
+        Ok(socket) => {
+            info!("network socket open on {}", address);
+            commands.insert_resource(NetworkSocket(socket));
+        }
+        Err(error) => warn!(
+            "Failed to open the network socket on {}: {}",
+            address, error
+        ),
+    }
+
+    let interval = match *network_role {
+        NetworkRole::Client(_) => INPUT_INTERVAL,
+        _ => SNAPSHOT_INTERVAL,
+    };
+
+    commands.insert_resource(NetworkTimer(Timer::from_seconds(interval, true)));
+    commands.insert_resource(ReceiveBuffer(vec![0; MAX_PACKET_SIZE]));
+    commands.insert_resource(SnapshotAssembly::default());
+    commands.insert_resource(UnsentInput::default());
+    commands.insert_resource(RemotePlayers::default());
+    commands.insert_resource(LocalPlayer(None));
+    commands.insert_resource(LatestArena(None));
+}
+
+fn close_socket(
+    mut commands: Commands,
+    network_role: Res<NetworkRole>,
+    socket: Option<Res<NetworkSocket>>,
+) {
+    if let (NetworkRole::Client(address), Some(socket)) = (*network_role, socket) {
+        send(&socket, address, &ClientMessage::Leave);
+    }
+
+    commands.remove_resource::<NetworkSocket>();
+    commands.remove_resource::<NetworkTimer>();
+    commands.remove_resource::<ReceiveBuffer>();
+    commands.remove_resource::<SnapshotAssembly>();
+    commands.remove_resource::<UnsentInput>();
+    commands.remove_resource::<RemotePlayers>();
+    commands.remove_resource::<LocalPlayer>();
+    commands.remove_resource::<LatestArena>();
+    commands.remove_resource::<HostPerks>();
+}
+
+fn host_receive(
+    mut commands: Commands,
+    network_role: Res<NetworkRole>,
+    socket: Option<Res<NetworkSocket>>,
+    buffer: Option<ResMut<ReceiveBuffer>>,
+    remote_players: Option<ResMut<RemotePlayers>>,
+    mut players: Query<&mut PlayerActions, With<Player>>,
+    player_count: Res<PlayerCount>,
+    textures: Res<TextureAssets>,
+    weapon_definitions: Res<WeaponDefinitions>,
+    arena_seed: Res<ArenaSeed>,
+) {
+    let (socket, mut buffer, mut remote_players) =
+        match (*network_role, socket, buffer, remote_players) {
+            (
+                NetworkRole::Host(_) | NetworkRole::Server(_),
+                Some(socket),
+                Some(buffer),
+                Some(remote_players),
+            ) => (socket, buffer, remote_players),
+            _ => return,
+        };
+
+    for (address, message) in receive::<ClientMessage>(&socket, &mut buffer) {
+        match message {
+            ClientMessage::Join { loadout } => {
+                let player = match remote_players.players.get(&address) {
+                    Some(player) => *player,
+                    None => {
+                        let id = PlayerId(player_count.0 + remote_players.joined);
+                        let player = spawn_player_with_id(
+                            &mut commands,
+                            &textures,
+                            &weapon_definitions,
+                            id,
+                            Vec2::ZERO,
+                            InputDevice::Network,
+                            &loadout,
+                        );
+
+                        info!("player joined from {}", address);
+
+                        remote_players.players.insert(address, player);
+                        remote_players.joined += 1;
+
+                        player
+                    }
+                };
+
+                send(
+                    &socket,
+                    address,
+                    &ServerMessage::Welcome {
+                        player: player.to_bits(),
+                        arena_seed: arena_seed.0,
+                    },
+                );
+            }
+            ClientMessage::Input {
+                movement,
+                aim,
+                weapon,
+                dash,
+                repair,
+                mirror,
+            } => {
+                let player = match remote_players.players.get(&address) {
+                    Some(player) => *player,
+                    None => continue,
+                };
+
+                if let Ok(mut actions) = players.get_mut(player) {
+                    actions.movement = movement.map(Vec2::from);
+                    actions.aim = aim.map(Vec2::from);
+                    actions.switch_weapon = Some(WeaponSwitch::Slot(weapon));
+                    actions.dash |= dash;
+                    actions.repair = repair;
+                    actions.mirror |= mirror;
+                }
+            }
+            ClientMessage::Leave => {
+                if let Some(player) = remote_players.players.remove(&address) {
+                    info!("player left from {}", address);
+
+                    // The player might have died already
+                    if players.get(player).is_ok() {
+                        commands.entity(player).despawn_recursive();
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Everything the host reads to make a [Snapshot]
+#[derive(SystemParam)]
+struct SnapshotParams<'w, 's> {
+    players: Query<
+        'w,
+        's,
+        (
+            Entity,
+            &'static PlayerId,
+            &'static Transform,
+            &'static Health,
+            &'static StatusEffects,
+        ),
+        With<Player>,
+    >,
+    weapons: Query<'w, 's, (&'static Weapon, &'static Parent)>,
+    enemies: Query<
+        'w,
+        's,
+        (
+            Entity,
+            &'static Transform,
+            &'static Health,
+            &'static Attack,
+            Option<&'static Boss>,
+            Option<&'static StatusEffects>,
+        ),
+        With<Enemy>,
+    >,
+    projectiles: Query<'w, 's, (Entity, &'static Transform, &'static Projectile)>,
+    mirrors: Query<'w, 's, (Entity, &'static Transform, &'static Mirror)>,
+    pickups: Query<'w, 's, (Entity, &'static Transform, &'static StatusPickup)>,
+    arena: ArenaParams<'w, 's>,
+    score: Res<'w, Score>,
+    perks: Res<'w, Perks>,
+    draft: Res<'w, PerkDraft>,
+    state: Res<'w, State<GameState>>,
+}
+
+impl SnapshotParams<'_, '_> {
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            score: self.score.0,
+            players: self
+                .players
+                .iter()
+                .map(
+                    |(entity, id, transform, health, status_effects)| PlayerState {
+                        entity: entity.to_bits(),
+                        id: id.0,
+                        position: transform.translation.xy().into(),
+                        rotation: rotation_z(transform),
+                        health: health.get_health(),
+                        beaming: self.weapons.iter().any(|(weapon, parent)| {
+                            parent.get() == entity && weapon.status == WeaponStatus::Beaming
+                        }),
+                        weapon: self
+                            .weapons
+                            .iter()
+                            .find(|(_, parent)| parent.get() == entity)
+                            .map(|(weapon, _)| weapon.slot)
+                            .unwrap_or_default(),
+                        status_effects: status_effects.states(),
+                    },
+                )
+                .collect(),
+            enemies: self
+                .enemies
+                .iter()
+                .map(
+                    |(entity, transform, health, attack, boss, status_effects)| EnemyState {
+                        entity: entity.to_bits(),
+                        position: transform.translation.xy().into(),
+                        rotation: rotation_z(transform),
+                        health: health.get_health(),
+                        ranged: matches!(attack.kind, AttackKind::Ranged { .. }),
+                        boss: boss.is_some(),
+                        status_effects: status_effects
+                            .map(StatusEffects::states)
+                            .unwrap_or_default(),
+                    },
+                )
+                .collect(),
+            projectiles: self
+                .projectiles
+                .iter()
+                .map(|(entity, transform, projectile)| ProjectileState {
+                    entity: entity.to_bits(),
+                    position: transform.translation.xy().into(),
+                    velocity: projectile.velocity.into(),
+                    damage: projectile.damage,
+                })
+                .collect(),
+            mirrors: self
+                .mirrors
+                .iter()
+                .map(|(entity, transform, mirror)| MirrorState {
+                    entity: entity.to_bits(),
+                    position: transform.translation.xy().into(),
+                    rotation: rotation_z(transform),
+                    knocked_over: mirror.knocked_over,
+                })
+                .collect(),
+            pickups: self
+                .pickups
+                .iter()
+                .map(|(entity, transform, pickup)| PickupState {
+                    entity: entity.to_bits(),
+                    position: transform.translation.xy().into(),
+                    effect: pickup.effect.clone(),
+                })
+                .collect(),
+            arena: self.arena.state(),
+            perks: HostPerks::new(&self.perks, &self.draft, &self.state),
+        }
+    }
+}
+
+fn host_send_snapshots(
+    network_role: Res<NetworkRole>,
+    socket: Option<Res<NetworkSocket>>,
+    remote_players: Option<Res<RemotePlayers>>,
+    timer: Option<ResMut<NetworkTimer>>,
+    world: SnapshotParams,
+    time: Res<Time>,
+    mut sequence: Local<u32>,
+) {
+    let (socket, remote_players, mut timer) = match (*network_role, socket, remote_players, timer) {
+        (
+            NetworkRole::Host(_) | NetworkRole::Server(_),
+            Some(socket),
+            Some(remote_players),
+            Some(timer),
+        ) => (socket, remote_players, timer),
+        _ => return,
+    };
+
+    if !timer.tick(time.delta()).just_finished() || remote_players.players.is_empty() {
+        return;
+    }
+
+    *sequence += 1;
+
+    let parts = match snapshot_parts(*sequence, &world.snapshot()) {
+        Ok(parts) => parts,
+        Err(error) => {
+            error!("Failed to send a snapshot: {}", error);
+            return;
+        }
+    };
+
+    for address in remote_players.players.keys() {
+        for part in parts.iter() {
+            send(&socket, *address, part);
+        }
+    }
+}
+
+fn client_send_input(
+    network_role: Res<NetworkRole>,
+    socket: Option<Res<NetworkSocket>>,
+    local_player: Option<Res<LocalPlayer>>,
+    timer: Option<ResMut<NetworkTimer>>,
+    unsent_input: Option<ResMut<UnsentInput>>,
+    mut players: Query<(Entity, &InputDevice, &mut PlayerActions)>,
+    weapons: Query<(&Weapon, &Parent)>,
+    progression: Res<Progression>,
+    unlocks: Res<Unlocks>,
+    time: Res<Time>,
+) {
+    let (address, socket, local_player, mut timer, mut unsent_input) =
+        match (*network_role, socket, local_player, timer, unsent_input) {
+            (
+                NetworkRole::Client(address),
+                Some(socket),
+                Some(local_player),
+                Some(timer),
+                Some(unsent_input),
+            ) => (address, socket, local_player, timer, unsent_input),
+            _ => return,
+        };
+
+    for (_, input_device, mut actions) in players.iter_mut() {
+        if *input_device != InputDevice::Network {
+            unsent_input.dash |= actions.dash;
+            // Only the host puts down mirrors, so the press is taken here
+            unsent_input.mirror |= std::mem::take(&mut actions.mirror);
+        }
+    }
+
+    if !timer.tick(time.delta()).just_finished() {
+        return;
+    }
+
+    // Keep asking until the host answers
+    if local_player.is_none() {
+        send(
+            &socket,
+            address,
+            &ClientMessage::Join {
+                loadout: progression.loadout(&unlocks),
+            },
+        );
+        return;
+    }
+
+    let unsent_input = std::mem::take(&mut *unsent_input);
+
+    for (player, input_device, actions) in players.iter() {
+        if *input_device == InputDevice::Network {
+            continue;
+        }
+
+        let weapon = weapons
+            .iter()
+            .find(|(_, parent)| parent.get() == player)
+            .map(|(weapon, _)| weapon.slot)
+            .unwrap_or_default();
+
+        send(
+            &socket,
+            address,
+            &ClientMessage::Input {
+                movement: actions.movement.map(Vec2::into),
+                aim: actions.aim.map(Vec2::into),
+                weapon,
+                dash: unsent_input.dash,
+                repair: actions.repair,
+                mirror: unsent_input.mirror,
+            },
+        );
+    }
+}
+
+fn client_receive(
+    mut commands: Commands,
+    network_role: Res<NetworkRole>,
+    socket: Option<Res<NetworkSocket>>,
+    buffer: Option<ResMut<ReceiveBuffer>>,
+    assembly: Option<ResMut<SnapshotAssembly>>,
+    local_player: Option<ResMut<LocalPlayer>>,
+    latest_arena: Option<ResMut<LatestArena>>,
+    mut players: Query<
+        (
+            Entity,
+            Option<&Replicated>,
+            &InputDevice,
+            &mut Transform,
+            &mut Health,
+            &mut PlayerActions,
+            &mut StatusEffects,
+        ),
+        (With<Player>, Without<Enemy>),
+    >,
+    mut enemies: Query<
+        (
+            Entity,
+            &Replicated,
+            &mut Transform,
+            &mut Health,
+            Option<&mut StatusEffects>,
+        ),
+        (With<Enemy>, Without<Player>),
+    >,
+    mut projectiles: Query<
+        (Entity, &Replicated, &mut Transform, &mut Projectile),
+        (Without<Enemy>, Without<Player>),
+    >,
+    mut weapons: Query<(&Parent, &mut Weapon)>,
+    mut mirrors: Query<(Entity, &Replicated, &mut Mirror, &mut Sprite)>,
+    pickups: Query<(Entity, &Replicated), With<StatusPickup>>,
+    mut score: ResMut<Score>,
+    textures: Res<TextureAssets>,
+    weapon_definitions: Res<WeaponDefinitions>,
+) {
+    let (socket, mut buffer, mut assembly, mut local_player, mut latest_arena) = match (
+        *network_role,
+        socket,
+        buffer,
+        assembly,
+        local_player,
+        latest_arena,
+    ) {
+        (
+            NetworkRole::Client(_),
+            Some(socket),
+            Some(buffer),
+            Some(assembly),
+            Some(local_player),
+            Some(latest_arena),
+        ) => (socket, buffer, assembly, local_player, latest_arena),
+        _ => return,
+    };
+
+    let mut latest_snapshot = None;
+
+    for (_, message) in receive::<ServerMessage>(&socket, &mut buffer) {
+        match message {
+            ServerMessage::Welcome { player, arena_seed } => {
+                if local_player.is_some() {
+                    continue;
+                }
+
+                local_player.0 = Some(player);
+
+                // Swaps the arena made up on this machine for the one of the host
+                commands.insert_resource(ArenaSeed(arena_seed));
+
+                for (entity, _, input_device, ..) in players.iter() {
+                    if *input_device != InputDevice::Network {
+                        commands.entity(entity).insert(Replicated(player));
+                    }
+                }
+            }
+            ServerMessage::SnapshotPart {
+                sequence,
+                index,
+                count,
+                data,
+            } => {
+                if let Some(snapshot) = assembly.add(sequence, index as usize, count as usize, data)
+                {
+                    latest_snapshot = Some(snapshot);
+                }
+            }
+        }
+    }
+
+    let (snapshot, local_player) = match (latest_snapshot, local_player.0) {
+        (Some(snapshot), Some(local_player)) => (snapshot, local_player),
+        _ => return,
+    };
+
+    score.0 = snapshot.score;
+    latest_arena.0 = Some(snapshot.arena);
+    commands.insert_resource(snapshot.perks);
+
+    // Players
+    let player_states = snapshot
+        .players
+        .iter()
+        .map(|state| (state.entity, state))
+        .collect::<HashMap<_, _>>();
+    let mut beaming = HashMap::new();
+
+    for (
+        entity,
+        replicated,
+        input_device,
+        mut transform,
+        mut health,
+        mut actions,
+        mut status_effects,
+    ) in players.iter_mut()
+    {
+        let replicated = match replicated {
+            Some(replicated) => replicated.0,
+            None => continue,
+        };
+
+        let state = match player_states.get(&replicated) {
+            Some(state) => state,
+            None => {
+                if replicated == local_player {
+                    // The host has removed our fallen player
+                    health.set_health(0.);
+                } else {
+                    commands.entity(entity).despawn_recursive();
+                }
+
+                continue;
+            }
+        };
+
+        let position = Vec2::from(state.position);
+
+        if *input_device == InputDevice::Network {
+            transform.translation = position.extend(transform.translation.z);
+            transform.rotation = Quat::from_rotation_z(state.rotation);
+            actions.switch_weapon = Some(WeaponSwitch::Slot(state.weapon));
+        } else if transform.translation.xy().distance(position) > RECONCILE_DISTANCE {
+            // The prediction went wrong
+            transform.translation = position.extend(transform.translation.z);
+        }
+
+        health.set_health(state.health);
+        // Also slows down or speeds up the prediction of the local player
+        status_effects.set_states(&state.status_effects);
+        beaming.insert(entity, state.beaming);
+    }
+
+    let known_players = players
+        .iter()
+        .filter_map(|(_, replicated, ..)| replicated.map(|replicated| replicated.0))
+        .collect::<Vec<_>>();
+
+    for state in snapshot.players.iter() {
+        if state.entity == local_player || known_players.contains(&state.entity) {
+            continue;
+        }
+
+        // Shifted by one so that they don't share the id of the local player
+        let player = spawn_player_with_id(
+            &mut commands,
+            &textures,
+            &weapon_definitions,
+            PlayerId(state.id + 1),
+            Vec2::from(state.position),
+            InputDevice::Network,
+            &Loadout::default(),
+        );
+
+        commands.entity(player).insert(Replicated(state.entity));
+    }
+
+    for (parent, mut weapon) in weapons.iter_mut() {
+        match (beaming.get(&parent.get()), &weapon.status) {
+            (Some(true), WeaponStatus::Idle) => {
+                weapon.beaming_time.reset();
+                weapon.status = WeaponStatus::Beaming;
+            }
+            (Some(false), WeaponStatus::Beaming) => {
+                weapon.cooldown.reset();
+                weapon.status = WeaponStatus::Idle;
+            }
+            _ => {}
+        }
+    }
+
+    // Enemies
+    let enemy_states = snapshot
+        .enemies
+        .iter()
+        .map(|state| (state.entity, state))
+        .collect::<HashMap<_, _>>();
+
+    for (entity, replicated, mut transform, mut health, status_effects) in enemies.iter_mut() {
+        match enemy_states.get(&replicated.0) {
+            Some(state) => {
+                transform.translation = Vec2::from(state.position).extend(transform.translation.z);
+                transform.rotation = Quat::from_rotation_z(state.rotation);
+                health.set_health(state.health);
+
+                if let Some(mut status_effects) = status_effects {
+                    status_effects.set_states(&state.status_effects);
+                }
+            }
+            None => commands.entity(entity).despawn_recursive(),
+        }
+    }
+
+    let known_enemies = enemies
+        .iter()
+        .map(|(_, replicated, ..)| replicated.0)
+        .collect::<Vec<_>>();
+
+    for state in snapshot.enemies.iter() {
+        if known_enemies.contains(&state.entity) {
+            continue;
+        }
+
+        let position = Vec2::from(state.position);
+
+        if state.boss {
+            let boss = spawn_boss(&mut commands, &textures, position);
+            commands.entity(boss).insert(Replicated(state.entity));
+            continue;
+        }
+
+        let enemy = if state.ranged {
+            EnemyBundle::ranged(position, &textures)
+        } else {
+            EnemyBundle::at(position, &textures)
+        };
+
+        commands
+            .spawn_bundle(enemy)
+            .insert(Replicated(state.entity));
+    }
+
+    // Projectiles keep flying on their own between the snapshots
+    let projectile_states = snapshot
+        .projectiles
+        .iter()
+        .map(|state| (state.entity, state))
+        .collect::<HashMap<_, _>>();
+
+    for (entity, replicated, mut transform, mut projectile) in projectiles.iter_mut() {
+        match projectile_states.get(&replicated.0) {
+            Some(state) => {
+                transform.translation = Vec2::from(state.position).extend(transform.translation.z);
+                projectile.velocity = Vec2::from(state.velocity);
+            }
+            None => commands.entity(entity).despawn_recursive(),
+        }
+    }
+
+    let known_projectiles = projectiles
+        .iter()
+        .map(|(_, replicated, ..)| replicated.0)
+        .collect::<Vec<_>>();
+
+    for state in snapshot.projectiles.iter() {
+        if known_projectiles.contains(&state.entity) {
+            continue;
+        }
+
+        commands
+            .spawn_bundle(ProjectileBundle::new(
+                Vec2::from(state.position),
+                Vec2::from(state.velocity),
+                state.damage,
+            ))
+            .insert(Replicated(state.entity));
+    }
+
+    // Mirrors don't move, only knocking them over is sent along
+    let mirror_states = snapshot
+        .mirrors
+        .iter()
+        .map(|state| (state.entity, state))
+        .collect::<HashMap<_, _>>();
+
+    for (entity, replicated, mut mirror, mut sprite) in mirrors.iter_mut() {
+        match mirror_states.get(&replicated.0) {
+            Some(state) => {
+                if state.knocked_over && !mirror.knocked_over {
+                    knock_over(&mut commands, entity, &mut mirror, &mut sprite);
+                }
+            }
+            None => commands.entity(entity).despawn_recursive(),
+        }
+    }
+
+    let known_mirrors = mirrors
+        .iter()
+        .map(|(_, replicated, ..)| replicated.0)
+        .collect::<Vec<_>>();
+
+    for state in snapshot.mirrors.iter() {
+        if known_mirrors.contains(&state.entity) {
+            continue;
+        }
+
+        let mirror = spawn_mirror(
+            &mut commands,
+            Transform::from_translation(Vec2::from(state.position).extend(1.5))
+                .with_rotation(Quat::from_rotation_z(state.rotation)),
+        );
+
+        commands.entity(mirror).insert(Replicated(state.entity));
+    }
+
+    // Pickups stay where they were dropped until collected or gone
+    let pickup_states = snapshot
+        .pickups
+        .iter()
+        .map(|state| state.entity)
+        .collect::<Vec<_>>();
+
+    for (entity, replicated) in pickups.iter() {
+        if !pickup_states.contains(&replicated.0) {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+
+    let known_pickups = pickups
+        .iter()
+        .map(|(_, replicated)| replicated.0)
+        .collect::<Vec<_>>();
+
+    for state in snapshot.pickups.iter() {
+        if known_pickups.contains(&state.entity) {
+            continue;
+        }
+
+        let pickup = spawn_pickup(
+            &mut commands,
+            Vec2::from(state.position),
+            state.effect.clone(),
+        );
+
+        commands.entity(pickup).insert(Replicated(state.entity));
+    }
+}
+
+/// Blows up the barrels and breaks the walls that are gone on the host,
+/// the walls still standing glow and crack like they do there and the windows keep their boards.
+/// Also keeps the [ArenaClock] in step with the host
+fn client_sync_arena(
+    mut commands: Commands,
+    mut explosion_events: EventWriter<ExplosionEvent>,
+    network_role: Res<NetworkRole>,
+    latest_arena: Option<Res<LatestArena>>,
+    arena_seed: Res<ArenaSeed>,
+    mut arena_clock: ResMut<ArenaClock>,
+    barrels: Query<(Entity, &ExplosiveBarrel, &Transform)>,
+    mut walls: Query<(Entity, &mut DestructibleWall, &mut Health), Without<Barricade>>,
+    mut barricades: Query<(&EnemySpawn, &mut Health), (With<Barricade>, Without<DestructibleWall>)>,
+) {
+    let latest_arena = match (*network_role, latest_arena) {
+        (NetworkRole::Client(_), Some(latest_arena)) => latest_arena,
+        _ => return,
+    };
+
+    let arena = match &latest_arena.0 {
+        Some(arena) => arena,
+        None => return,
+    };
+
+    // Until the arena of the host is built, the barrels here belong to another one
+    if arena.seed != arena_seed.0 || arena_seed.is_changed() {
+        return;
+    }
+
+    // Runs on by itself between the snapshots
+    if latest_arena.is_changed() {
+        arena_clock.0 = arena.clock;
+    }
+
+    for (entity, barrel, transform) in barrels.iter() {
+        if arena.barrels.contains(&barrel.index) {
+            continue;
+        }
+
+        commands.entity(entity).despawn_recursive();
+
+        explosion_events.send(ExplosionEvent {
+            position: transform.translation.xy(),
+            source: DamageSource {
+                kind: DamageKind::Explosion,
+                entity,
+            },
+        });
+    }
+
+    for (entity, mut wall, mut health) in walls.iter_mut() {
+        match arena.walls.iter().find(|state| state.index == wall.index) {
+            Some(state) => {
+                wall.heat = state.heat;
+                health.set_health(state.health);
+            }
+            None => break_wall(&mut commands, entity),
+        }
+    }
+
+    for (window, mut health) in barricades.iter_mut() {
+        if let Some(state) = arena
+            .barricades
+            .iter()
+            .find(|state| state.window == window.index)
+        {
+            Barricade::set_boards(&mut health, state.boards);
+        }
+    }
+}
@@ -0,0 +1,159 @@
+use crate::{
+    loading::FontAssets,
+    network::NetworkRole,
+    perks::{PerkDefinitions, Perks},
+    GameState,
+};
+use bevy::prelude::*;
+
+pub struct PausePlugin;
+
+/// This plugin pauses the game with Escape or the start button and shows the perks taken so far.
+/// The pause screen is pushed on top of `GameState::Playing`, so nothing is torn down while paused.
+/// Only the host can pause a networked game, the clients play in its simulation.
+impl Plugin for PausePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::on_update(GameState::Playing).with_system(pause.after("check_if_dead")),
+        )
+        .add_system_set(SystemSet::on_enter(GameState::Paused).with_system(setup_pause_menu))
+        .add_system_set(SystemSet::on_update(GameState::Paused).with_system(resume))
+        .add_system_set(SystemSet::on_exit(GameState::Paused).with_system(cleanup_pause_menu));
+    }
+}
+
+#[derive(Component)]
+struct PauseMenu;
+
+/// Whether the pause button was just pressed, clearing it so it isn't seen again on the other side
+fn take_pause_input(
+    keyboard_input: &mut Input<KeyCode>,
+    gamepad_buttons: &mut Input<GamepadButton>,
+    gamepads: &Gamepads,
+) -> bool {
+    let mut pressed = keyboard_input.clear_just_pressed(KeyCode::Escape);
+
+    for gamepad in gamepads.iter() {
+        pressed |= gamepad_buttons
+            .clear_just_pressed(GamepadButton::new(*gamepad, GamepadButtonType::Start));
+    }
+
+    pressed
+}
+
+fn pause(
+    mut state: ResMut<State<GameState>>,
+    mut keyboard_input: ResMut<Input<KeyCode>>,
+    mut gamepad_buttons: ResMut<Input<GamepadButton>>,
+    gamepads: Res<Gamepads>,
+    network_role: Res<NetworkRole>,
+) {
+    if !network_role.is_authority() {
+        return;
+    }
+
+    // A game over or perk draft queued on the same frame wins, there's nothing to pause then
+    if take_pause_input(&mut keyboard_input, &mut gamepad_buttons, &gamepads) {
+        let _ = state.push(GameState::Paused);
+    }
+}
+
+fn resume(
+    mut state: ResMut<State<GameState>>,
+    mut keyboard_input: ResMut<Input<KeyCode>>,
+    mut gamepad_buttons: ResMut<Input<GamepadButton>>,
+    gamepads: Res<Gamepads>,
+) {
+    if take_pause_input(&mut keyboard_input, &mut gamepad_buttons, &gamepads) {
+        state.pop().unwrap();
+    }
+}
+
+fn setup_pause_menu(
+    mut commands: Commands,
+    font_assets: Res<FontAssets>,
+    perks: Res<Perks>,
+    definitions: Res<PerkDefinitions>,
+) {
+    let text_style = |font_size: f32, color: Color| TextStyle {
+        font: font_assets.fira_sans.clone(),
+        font_size,
+        color,
+    };
+
+    let taken = definitions
+        .iter()
+        .zip(perks.stacks.iter())
+        .filter(|(_, stacks)| **stacks > 0)
+        .collect::<Vec<_>>();
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                flex_direction: FlexDirection::ColumnReverse,
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            color: Color::rgba(0., 0., 0., 0.5).into(),
+            ..default()
+        })
+        .with_children(|parent| {
+            parent.spawn_bundle(
+                TextBundle::from_section("Paused", text_style(72.0, Color::WHITE)).with_style(
+                    Style {
+                        margin: UiRect {
+                            bottom: Val::Percent(3.),
+                            ..default()
+                        },
+                        ..default()
+                    },
+                ),
+            );
+
+            parent.spawn_bundle(TextBundle::from_section(
+                "Perks",
+                text_style(36.0, Color::WHITE),
+            ));
+
+            if taken.is_empty() {
+                parent.spawn_bundle(TextBundle::from_section(
+                    "None yet",
+                    text_style(20.0, Color::rgb(0.7, 0.7, 0.7)),
+                ));
+            }
+
+            for (definition, stacks) in taken {
+                parent.spawn_bundle(TextBundle::from_sections([
+                    TextSection::new(
+                        format!("{} x{}: ", definition.name, stacks),
+                        text_style(20.0, Color::WHITE),
+                    ),
+                    TextSection::new(
+                        definition.description.clone(),
+                        text_style(20.0, Color::rgb(0.7, 0.7, 0.7)),
+                    ),
+                ]));
+            }
+
+            parent.spawn_bundle(
+                TextBundle::from_section(
+                    "Press Escape to resume",
+                    text_style(20.0, Color::rgb(0.5, 0.5, 0.5)),
+                )
+                .with_style(Style {
+                    margin: UiRect {
+                        top: Val::Percent(3.),
+                        ..default()
+                    },
+                    ..default()
+                }),
+            );
+        })
+        .insert(PauseMenu);
+}
+
+fn cleanup_pause_menu(mut commands: Commands, menu: Query<Entity, With<PauseMenu>>) {
+    commands.entity(menu.single()).despawn_recursive();
+}
@@ -0,0 +1,544 @@
+use std::time::Duration;
+
+use crate::{
+    character::{Health, Rotation},
+    enemy::EnemyDamagedEvent,
+    loading::FontAssets,
+    network::NetworkRole,
+    player::{Dash, Player},
+    progression::{Progression, Unlocks},
+    score::Score,
+    weapon::Weapon,
+    GameState,
+};
+use bevy::prelude::*;
+use rand::seq::IteratorRandom;
+use serde::{Deserialize, Serialize};
+
+pub struct PerkPlugin;
+
+/// This plugin pauses the game every [PERK_SCORE_STEP] points to let the players pick one of
+/// [PERK_CHOICES] random perks defined in `assets/perks.ron`.
+/// The draft is pushed on top of `GameState::Playing`, so the run carries on right where it was after picking.
+/// In a networked game the host picks, the clients are shown its draft and get the perks it took.
+impl Plugin for PerkPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(load_perk_definitions())
+            .init_resource::<ButtonColors>()
+            .add_system_set(SystemSet::on_enter(GameState::Playing).with_system(setup_perks))
+            .add_system_set(
+                SystemSet::on_update(GameState::Playing)
+                    .with_system(start_perk_draft.after("check_if_dead"))
+                    .with_system(perk_new_players)
+                    .with_system(apply_weapon_perks)
+                    .with_system(lifesteal),
+            )
+            .add_system_set(
+                SystemSet::on_in_stack_update(GameState::Playing).with_system(follow_host_perks),
+            )
+            .add_system_set(SystemSet::on_enter(GameState::PerkDraft).with_system(setup_perk_draft))
+            .add_system_set(SystemSet::on_update(GameState::PerkDraft).with_system(choose_perk))
+            .add_system_set(
+                SystemSet::on_exit(GameState::PerkDraft).with_system(cleanup_perk_draft),
+            );
+    }
+}
+
+pub const PERK_SCORE_STEP: u32 = 2500;
+/// Perks to choose from in every draft
+pub const PERK_CHOICES: usize = 3;
+/// Every stack of a perk gives this fraction of what the one before it gave
+pub const PERK_DIMINISHING_RETURNS: f32 = 0.7;
+
+#[derive(Deserialize, Clone, Copy, Debug)]
+pub enum PerkEffect {
+    /// Raises the max health of the players by this much
+    MaxHealth(f32),
+    /// Makes the players turn faster by this fraction
+    RotationSpeed(f32),
+    /// Takes this fraction off the time it takes weapons to charge
+    Cooldown(f32),
+    /// Makes weapons fire longer by this fraction
+    BeamingTime(f32),
+    /// Lets every ray go through whatever it hits
+    Pierce,
+    /// Heals the player this much for every kill
+    Lifesteal(f32),
+    /// One more dash before having to wait for the cooldown
+    DashCharge,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct PerkDefinition {
+    pub name: String,
+    pub description: String,
+    pub effect: PerkEffect,
+    /// How many times the perk can be taken, any number of times if not set
+    #[serde(default)]
+    pub max_stacks: Option<u32>,
+}
+
+/// The perks defined in `assets/perks.ron`
+#[derive(Deref)]
+pub struct PerkDefinitions(pub Vec<PerkDefinition>);
+
+/// The perks taken during the current run and what they add up to
+#[derive(Default)]
+pub struct Perks {
+    /// How many times each of the [PerkDefinitions] was taken
+    pub stacks: Vec<u32>,
+    cooldown_reduction: f32,
+    beaming_time_bonus: f32,
+    pierce: bool,
+    lifesteal: f32,
+}
+
+/// When the next draft comes and what it offers
+pub struct PerkDraft {
+    next_score: u32,
+    /// Indices into [PerkDefinitions]
+    choices: Vec<usize>,
+}
+
+/// The perks of the host and the draft it is in, sent to the clients, see [crate::network]
+#[derive(Serialize, Deserialize)]
+pub struct HostPerks {
+    /// Same as [Perks::stacks] on the host
+    pub stacks: Vec<u32>,
+    /// The choices of the draft the host is picking from
+    pub draft: Option<Vec<usize>>,
+}
+
+impl HostPerks {
+    pub fn new(perks: &Perks, draft: &PerkDraft, state: &State<GameState>) -> Self {
+        Self {
+            stacks: perks.stacks.clone(),
+            draft: (*state.current() == GameState::PerkDraft).then(|| draft.choices.clone()),
+        }
+    }
+}
+
+#[derive(Component)]
+struct PerkDraftMenu;
+
+/// Takes the perk with this index in [PerkDefinitions]
+#[derive(Component)]
+struct PerkButton(usize);
+
+struct ButtonColors {
+    normal: UiColor,
+    hovered: UiColor,
+}
+
+impl Default for ButtonColors {
+    fn default() -> Self {
+        ButtonColors {
+            normal: Color::rgb(0.15, 0.15, 0.15).into(),
+            hovered: Color::rgb(0.25, 0.25, 0.25).into(),
+        }
+    }
+}
+
+fn load_perk_definitions() -> PerkDefinitions {
+    let definitions: Vec<PerkDefinition> = ron::from_str(include_str!("../assets/perks.ron"))
+        .expect("Failed to parse the perk definitions");
+
+    PerkDefinitions(definitions)
+}
+
+/// What a perk gives when taken with the given number of stacks already
+fn diminished(value: f32, stacks: u32) -> f32 {
+    value * PERK_DIMINISHING_RETURNS.powi(stacks as i32)
+}
+
+/// Adds the perk with this index in [PerkDefinitions] to the bonuses of the run,
+/// what it does to the players is done by [apply_player_perk]
+fn take_perk(perks: &mut Perks, definitions: &PerkDefinitions, index: usize) {
+    let stacks = perks.stacks[index];
+
+    match definitions[index].effect {
+        PerkEffect::Cooldown(fraction) => {
+            perks.cooldown_reduction += diminished(fraction, stacks);
+        }
+        PerkEffect::BeamingTime(fraction) => {
+            perks.beaming_time_bonus += diminished(fraction, stacks);
+        }
+        PerkEffect::Pierce => {
+            perks.pierce = true;
+        }
+        PerkEffect::Lifesteal(amount) => {
+            perks.lifesteal += diminished(amount, stacks);
+        }
+        PerkEffect::MaxHealth(_) | PerkEffect::RotationSpeed(_) | PerkEffect::DashCharge => {}
+    }
+
+    perks.stacks[index] += 1;
+}
+
+/// Applies a perk taken with the given number of stacks already to one player
+fn apply_player_perk(
+    effect: PerkEffect,
+    stacks: u32,
+    health: &mut Health,
+    rotation: &mut Rotation,
+    dash: &mut Dash,
+) {
+    match effect {
+        PerkEffect::MaxHealth(amount) => {
+            health.raise_max_health(diminished(amount, stacks));
+        }
+        PerkEffect::RotationSpeed(fraction) => {
+            rotation.rotation_speed *= 1. + diminished(fraction, stacks);
+        }
+        PerkEffect::DashCharge => {
+            dash.add_charge();
+        }
+        _ => {}
+    }
+}
+
+/// Gives every player the perk with this index in [PerkDefinitions]
+fn give_perk(
+    perks: &mut Perks,
+    definitions: &PerkDefinitions,
+    index: usize,
+    players: &mut Query<(&mut Health, &mut Rotation, &mut Dash), With<Player>>,
+) {
+    for (mut health, mut rotation, mut dash) in players.iter_mut() {
+        apply_player_perk(
+            definitions[index].effect,
+            perks.stacks[index],
+            &mut health,
+            &mut rotation,
+            &mut dash,
+        );
+    }
+
+    take_perk(perks, definitions, index);
+}
+
+fn setup_perks(
+    mut commands: Commands,
+    definitions: Res<PerkDefinitions>,
+    progression: Res<Progression>,
+    unlocks: Res<Unlocks>,
+    network_role: Res<NetworkRole>,
+) {
+    let mut perks = Perks {
+        stacks: vec![0; definitions.len()],
+        ..default()
+    };
+
+    // Clients get the starting perks of the host instead, see [follow_host_perks]
+    let starting_perks: Vec<&str> = if network_role.is_authority() {
+        progression.starting_perks(&unlocks).collect()
+    } else {
+        Vec::new()
+    };
+
+    // Bought on the upgrade screen, the players get them once they are spawned
+    for name in starting_perks {
+        match definitions
+            .iter()
+            .position(|definition| definition.name == name)
+        {
+            Some(index) => take_perk(&mut perks, &definitions, index),
+            None => warn!("There is no perk called {}", name),
+        }
+    }
+
+    commands.insert_resource(perks);
+
+    commands.insert_resource(PerkDraft {
+        next_score: PERK_SCORE_STEP,
+        choices: Vec::new(),
+    });
+}
+
+fn start_perk_draft(
+    mut state: ResMut<State<GameState>>,
+    mut draft: ResMut<PerkDraft>,
+    perks: Res<Perks>,
+    definitions: Res<PerkDefinitions>,
+    score: Res<Score>,
+    network_role: Res<NetworkRole>,
+) {
+    // Clients play along with the perks of the host
+    if !network_role.is_authority() || score.0 < draft.next_score {
+        return;
+    }
+
+    draft.choices = definitions
+        .iter()
+        .enumerate()
+        .filter(|(index, definition)| {
+            definition
+                .max_stacks
+                .map_or(true, |max_stacks| perks.stacks[*index] < max_stacks)
+        })
+        .map(|(index, _)| index)
+        .choose_multiple(&mut rand::thread_rng(), PERK_CHOICES);
+
+    // Every perk is maxed out
+    if draft.choices.is_empty() {
+        draft.next_score += PERK_SCORE_STEP;
+        return;
+    }
+
+    // Pausing on the same frame came first, the draft is tried again once back in the game
+    if state.push(GameState::PerkDraft).is_ok() {
+        draft.next_score += PERK_SCORE_STEP;
+    }
+}
+
+fn setup_perk_draft(
+    mut commands: Commands,
+    font_assets: Res<FontAssets>,
+    button_colors: Res<ButtonColors>,
+    draft: Res<PerkDraft>,
+    perks: Res<Perks>,
+    definitions: Res<PerkDefinitions>,
+    network_role: Res<NetworkRole>,
+) {
+    let title = if network_role.is_authority() {
+        "Choose a perk"
+    } else {
+        "The host is choosing a perk"
+    };
+
+    let text_style = |font_size: f32, color: Color| TextStyle {
+        font: font_assets.fira_sans.clone(),
+        font_size,
+        color,
+    };
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                flex_direction: FlexDirection::ColumnReverse,
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            color: Color::rgba(0., 0., 0., 0.5).into(),
+            ..default()
+        })
+        .with_children(|parent| {
+            parent.spawn_bundle(
+                TextBundle::from_section(title, text_style(60.0, Color::WHITE)).with_style(Style {
+                    margin: UiRect {
+                        bottom: Val::Percent(3.),
+                        ..default()
+                    },
+                    ..default()
+                }),
+            );
+
+            parent
+                .spawn_bundle(NodeBundle {
+                    color: Color::NONE.into(),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    for (number, index) in draft.choices.iter().enumerate() {
+                        let definition = &definitions[*index];
+
+                        parent
+                            .spawn_bundle(ButtonBundle {
+                                style: Style {
+                                    size: Size::new(Val::Px(240.0), Val::Px(140.0)),
+                                    margin: UiRect::all(Val::Px(10.)),
+                                    padding: UiRect::all(Val::Px(10.)),
+                                    flex_direction: FlexDirection::ColumnReverse,
+                                    justify_content: JustifyContent::Center,
+                                    align_items: AlignItems::Center,
+                                    ..default()
+                                },
+                                color: button_colors.normal,
+                                ..default()
+                            })
+                            .with_children(|parent| {
+                                parent.spawn_bundle(TextBundle::from_section(
+                                    format!("{}. {}", number + 1, definition.name),
+                                    text_style(28.0, Color::rgb(0.9, 0.9, 0.9)),
+                                ));
+
+                                parent.spawn_bundle(TextBundle::from_section(
+                                    definition.description.clone(),
+                                    text_style(18.0, Color::rgb(0.7, 0.7, 0.7)),
+                                ));
+
+                                let stacks = perks.stacks[*index];
+
+                                if stacks > 0 {
+                                    parent.spawn_bundle(TextBundle::from_section(
+                                        format!("Taken {}x", stacks),
+                                        text_style(16.0, Color::rgb(0.5, 0.5, 0.5)),
+                                    ));
+                                }
+                            })
+                            .insert(PerkButton(*index));
+                    }
+                });
+        })
+        .insert(PerkDraftMenu);
+}
+
+fn choose_perk(
+    mut state: ResMut<State<GameState>>,
+    button_colors: Res<ButtonColors>,
+    mut interaction_query: Query<
+        (&Interaction, &mut UiColor, &PerkButton),
+        (Changed<Interaction>, With<Button>),
+    >,
+    mut keyboard_input: ResMut<Input<KeyCode>>,
+    draft: Res<PerkDraft>,
+    mut perks: ResMut<Perks>,
+    definitions: Res<PerkDefinitions>,
+    mut players: Query<(&mut Health, &mut Rotation, &mut Dash), With<Player>>,
+    network_role: Res<NetworkRole>,
+) {
+    let mut chosen = None;
+
+    for (interaction, mut color, button) in &mut interaction_query {
+        match *interaction {
+            Interaction::Clicked => {
+                chosen = Some(button.0);
+            }
+            Interaction::Hovered => {
+                *color = button_colors.hovered;
+            }
+            Interaction::None => {
+                *color = button_colors.normal;
+            }
+        }
+    }
+
+    // The number keys pick the perks in order, without also switching weapons once back in the game
+    for (number, key) in [KeyCode::Key1, KeyCode::Key2, KeyCode::Key3]
+        .into_iter()
+        .enumerate()
+    {
+        if keyboard_input.clear_just_pressed(key) {
+            chosen = chosen.or_else(|| draft.choices.get(number).copied());
+        }
+    }
+
+    let index = match chosen {
+        // Clients wait for the host to pick, see [follow_host_perks]
+        Some(index) if network_role.is_authority() => index,
+        _ => return,
+    };
+
+    give_perk(&mut perks, &definitions, index, &mut players);
+
+    state.pop().unwrap();
+}
+
+/// Takes the perks the host took and opens and closes the draft along with it
+fn follow_host_perks(
+    mut state: ResMut<State<GameState>>,
+    host_perks: Option<Res<HostPerks>>,
+    mut draft: ResMut<PerkDraft>,
+    mut perks: ResMut<Perks>,
+    definitions: Res<PerkDefinitions>,
+    mut players: Query<(&mut Health, &mut Rotation, &mut Dash), With<Player>>,
+) {
+    let host_perks = match host_perks {
+        Some(host_perks) if host_perks.is_changed() => host_perks,
+        _ => return,
+    };
+
+    for (index, host_stacks) in host_perks.stacks.iter().enumerate() {
+        while perks
+            .stacks
+            .get(index)
+            .map_or(false, |stacks| stacks < host_stacks)
+        {
+            give_perk(&mut perks, &definitions, index, &mut players);
+        }
+    }
+
+    let drafting = *state.current() == GameState::PerkDraft;
+
+    // Whatever else is queued on this frame goes first, the next snapshot tries again
+    match &host_perks.draft {
+        Some(choices) if !drafting => {
+            draft.choices = choices.clone();
+            let _ = state.push(GameState::PerkDraft);
+        }
+        None if drafting => {
+            let _ = state.pop();
+        }
+        _ => {}
+    }
+}
+
+fn cleanup_perk_draft(mut commands: Commands, menu: Query<Entity, With<PerkDraftMenu>>) {
+    commands.entity(menu.single()).despawn_recursive();
+}
+
+/// Gives players spawned after perks were taken, like the ones at the start of the run, what the others got
+fn perk_new_players(
+    mut players: Query<(&mut Health, &mut Rotation, &mut Dash), Added<Player>>,
+    perks: Res<Perks>,
+    definitions: Res<PerkDefinitions>,
+) {
+    for (mut health, mut rotation, mut dash) in players.iter_mut() {
+        for (definition, stacks) in definitions.iter().zip(perks.stacks.iter()) {
+            for stack in 0..*stacks {
+                apply_player_perk(
+                    definition.effect,
+                    stack,
+                    &mut health,
+                    &mut rotation,
+                    &mut dash,
+                );
+            }
+        }
+    }
+}
+
+/// Weapons are made anew when switching, so the perks are put on them again whenever they change
+fn apply_weapon_perks(mut weapons: Query<&mut Weapon>, perks: Res<Perks>) {
+    for mut weapon in weapons.iter_mut() {
+        if !weapon.is_changed() && !perks.is_changed() {
+            continue;
+        }
+
+        let cooldown = weapon.definition.cooldown * (1. - perks.cooldown_reduction);
+        let firing_time = weapon.definition.firing_time * (1. + perks.beaming_time_bonus);
+
+        weapon
+            .cooldown
+            .set_duration(Duration::from_secs_f32(cooldown));
+        weapon
+            .beaming_time
+            .set_duration(Duration::from_secs_f32(firing_time));
+        weapon.pierces = weapon.definition.kind.pierces() || perks.pierce;
+    }
+}
+
+fn lifesteal(
+    mut enemy_damaged_events: EventReader<EnemyDamagedEvent>,
+    weapons: Query<&Parent, With<Weapon>>,
+    mut players: Query<&mut Health, With<Player>>,
+    perks: Res<Perks>,
+) {
+    if perks.lifesteal <= 0. {
+        return;
+    }
+
+    for event in enemy_damaged_events.iter().filter(|event| event.killed) {
+        // The player whose weapon made the kill
+        let player = match weapons.get(event.source.entity) {
+            Ok(parent) => parent.get(),
+            Err(_) => continue,
+        };
+
+        if let Ok(mut health) = players.get_mut(player) {
+            health.heal(perks.lifesteal);
+        }
+    }
+}
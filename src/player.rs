@@ -1,8 +1,13 @@
-use crate::actions::Actions;
-use crate::camera::MainCamera;
+use crate::actions::{InputDevice, PlayerActions};
+use crate::camera::{screen_size, MainCamera};
+use crate::enemy::Enemy;
 use crate::character::{Health, Movement, Rotation};
+use crate::lighting::PointLight2d;
 use crate::loading::{TextureAssets, AudioAssets};
-use crate::weapon::{Weapon, WeaponBundle};
+use crate::progression::{Loadout, Progression, Unlocks};
+use crate::settings::Settings;
+use crate::status::StatusEffects;
+use crate::weapon::{LaserColor, LockedWeapons, Weapon, WeaponBundle, WeaponDefinitions};
 use crate::GameState;
 use crate::{WALL_HEIGHT, WALL_WIDTH};
 use bevy::prelude::*;
@@ -12,13 +17,53 @@ use rand::seq::SliceRandom;
 use crate::GAME_AREA_HEIGHT;
 use crate::GAME_AREA_WIDTH;
 use bevy::math::Vec3Swizzles;
-use std::f32::consts::PI;
+use std::f32::consts::{PI, TAU};
+use std::time::Duration;
 
 pub struct PlayerPlugin;
 
 #[derive(Component)]
 pub struct Player;
 
+/// Tells the players apart in local co-op, starting at 0
+#[derive(Component, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct PlayerId(pub usize);
+
+impl PlayerId {
+    pub fn color(&self) -> Color {
+        match self.0 {
+            0 => Color::WHITE,
+            _ => Color::rgb(0.55, 0.8, 1.),
+        }
+    }
+}
+
+/// How many players take part in the next run, 2 for local co-op
+#[derive(Deref, DerefMut)]
+pub struct PlayerCount(pub usize);
+
+/// Space kept around the players when the camera zooms out to fit all of them
+pub const CAMERA_ZOOM_MARGIN: f32 = 150.;
+pub const CAMERA_ZOOM_MAX: f32 = 1.6;
+pub const CAMERA_ZOOM_SPEED: f32 = 3.;
+
+/// Enemies further away than this are not aimed at by the aim assist
+pub const AIM_ASSIST_RANGE: f32 = 600.;
+/// Degrees to each side of the aim the aim assist looks for enemies in
+pub const AIM_ASSIST_CONE: f32 = 30.;
+
+pub const DASH_SPEED: f32 = 600.;
+pub const DASH_TIME: f32 = 0.15;
+pub const DASH_COOLDOWN: f32 = 1.5;
+/// Seconds the player can't get hurt for after starting a dash
+pub const DASH_INVULNERABILITY: f32 = 0.3;
+pub const AFTERIMAGE_INTERVAL: f32 = 0.03;
+pub const AFTERIMAGE_LIFETIME: f32 = 0.25;
+
+pub struct PlayerDamagedEvent {
+    pub amount: f32,
+}
+
 #[derive(Component, Deref, DerefMut)]
 pub struct FootstepTimer(Timer);
 
@@ -28,98 +73,203 @@ pub struct PlayerBundle {
     sprite_bundle: SpriteBundle,
     name: Name,
     player: Player,
+    player_id: PlayerId,
+    input_device: InputDevice,
+    actions: PlayerActions,
     health: Health,
     movement: Movement,
+    status_effects: StatusEffects,
     rotation: Rotation,
     footstep_timer: FootstepTimer,
+    dash: Dash,
+    light: PointLight2d,
 }
 
+#[derive(Component)]
+pub struct Dash {
+    direction: Vec2,
+    /// Runs while dashing
+    time: Timer,
+    /// Runs while recharging a dash
+    pub cooldown: Timer,
+    invulnerability: Timer,
+    afterimage_timer: Timer,
+    /// Dashes left before having to wait for the cooldown
+    charges: u32,
+    max_charges: u32,
+}
+
+impl Default for Dash {
+    fn default() -> Self {
+        // Ready to dash right away
+        let finished_timer = |seconds: f32| {
+            let mut timer = Timer::from_seconds(seconds, false);
+            timer.tick(Duration::from_secs_f32(seconds));
+            timer
+        };
+
+        Self {
+            direction: Vec2::ZERO,
+            time: finished_timer(DASH_TIME),
+            cooldown: finished_timer(DASH_COOLDOWN),
+            invulnerability: finished_timer(DASH_INVULNERABILITY),
+            afterimage_timer: Timer::from_seconds(AFTERIMAGE_INTERVAL, true),
+            charges: 1,
+            max_charges: 1,
+        }
+    }
+}
+
+impl Dash {
+    pub fn is_dashing(&self) -> bool {
+        !self.time.finished()
+    }
+
+    pub fn is_invulnerable(&self) -> bool {
+        !self.invulnerability.finished()
+    }
+
+    /// One more dash before having to wait for the cooldown, ready right away
+    pub fn add_charge(&mut self) {
+        self.max_charges += 1;
+        self.charges += 1;
+    }
+}
+
+/// Fading copy of the player left behind while dashing
+#[derive(Component, Deref, DerefMut)]
+struct Afterimage(Timer);
+
 /// This plugin handles player related stuff like movement
 /// Player logic is only active during the State `GameState::Playing`
 impl Plugin for PlayerPlugin {
     fn build(&self, app: &mut App) {
-        app.add_system_set(SystemSet::on_enter(GameState::Playing).with_system(spawn_player))
+        app.add_event::<PlayerDamagedEvent>()
+            .insert_resource(PlayerCount(1))
+            .add_system_set(SystemSet::on_enter(GameState::Playing).with_system(spawn_player))
             .add_system_set(
                 SystemSet::on_update(GameState::Playing)
                     .with_system(move_player.label("player_movement"))
+                    .with_system(dash.label("player_movement").after("player_actions"))
+                    .with_system(fade_afterimages)
                     .with_system(aim_player.after("player_movement"))
                     .with_system(camera_follow.after("player_movement"))
-                    .with_system(check_if_dead),
+                    .with_system(check_if_dead.label("check_if_dead")),
             )
-            .add_system_set(SystemSet::on_exit(GameState::Playing).with_system(drop_player));
+            .add_system_set(
+                SystemSet::on_exit(GameState::Playing)
+                    .with_system(drop_player)
+                    .with_system(drop_afterimages),
+            );
     }
 }
 
-fn spawn_player(mut commands: Commands, textures: Res<TextureAssets>) {
-    commands
-        .spawn_bundle(PlayerBundle {
-            sprite_bundle: SpriteBundle {
-                texture: textures.player_texture.clone(),
-                transform: Transform::from_translation(Vec3::new(0., 0., 5.))
-                    .with_scale(Vec3::new(0.25, -0.25, 1.)),
+fn spawn_player(
+    mut commands: Commands,
+    textures: Res<TextureAssets>,
+    weapon_definitions: Res<WeaponDefinitions>,
+    player_count: Res<PlayerCount>,
+    progression: Res<Progression>,
+    unlocks: Res<Unlocks>,
+) {
+    let loadout = progression.loadout(&unlocks);
+
+    for id in 0..player_count.0 {
+        // Side by side around the center
+        let x = (id as f32 - (player_count.0 - 1) as f32 / 2.) * 60.;
+
+        // The first player plays with keyboard and mouse, the others with gamepads
+        let input_device = match id {
+            0 => InputDevice::KeyboardMouse,
+            index => InputDevice::Gamepad(index - 1),
+        };
+
+        spawn_player_with_id(
+            &mut commands,
+            &textures,
+            &weapon_definitions,
+            PlayerId(id),
+            Vec2::new(x, 0.),
+            input_device,
+            &loadout,
+        );
+    }
+}
+
+pub fn spawn_player_with_id(
+    commands: &mut Commands,
+    textures: &TextureAssets,
+    weapon_definitions: &WeaponDefinitions,
+    id: PlayerId,
+    position: Vec2,
+    input_device: InputDevice,
+    loadout: &Loadout,
+) -> Entity {
+    let mut player = commands.spawn_bundle(PlayerBundle {
+        sprite_bundle: SpriteBundle {
+            sprite: Sprite {
+                color: id.color(),
                 ..default()
             },
-            name: Name::new("Player"),
-            player: Player,
-            health: Health::new(100.0),
-            movement: Movement { speed: 80. },
-            rotation: Rotation {
-                rotation_speed: 1.15,
-            },
-            footstep_timer: FootstepTimer(Timer::from_seconds(0.5, true)),
-        })
+            texture: textures.player_texture.clone(),
+            transform: Transform::from_translation(position.extend(5.))
+                .with_scale(Vec3::new(0.25, -0.25, 1.)),
+            ..default()
+        },
+        name: Name::new(format!("Player {}", id.0 + 1)),
+        player: Player,
+        player_id: id,
+        input_device,
+        actions: PlayerActions::default(),
+        health: Health::new(loadout.max_health),
+        movement: Movement::new(loadout.speed).with_acceleration(500., 700.),
+        status_effects: StatusEffects::default(),
+        rotation: Rotation {
+            rotation_speed: loadout.rotation_speed,
+        },
+        footstep_timer: FootstepTimer(Timer::from_seconds(0.5, true)),
+        dash: Dash::default(),
+        light: PointLight2d {
+            color: Color::rgb(1., 0.9, 0.75),
+            radius: 220.,
+            intensity: 0.9,
+            casts_shadows: true,
+        },
+    });
+
+    if let Some(color) = loadout.laser_color {
+        player.insert(LaserColor(color));
+    }
+
+    player.insert(LockedWeapons(loadout.locked_weapons.clone()));
+
+    player
         .with_children(|parent| {
             parent.spawn_bundle(WeaponBundle {
-                weapon: Weapon::default(),
+                weapon: Weapon::new(0, weapon_definitions),
             });
-        });
+        })
+        .id()
 }
 
 fn move_player(
-    mut player_query: Query<(&mut Transform, &Handle<Image>, &Movement, &mut FootstepTimer), With<Player>>,
+    mut player_query: Query<(&mut Transform, &Handle<Image>, &mut Movement, &mut FootstepTimer, &PlayerActions), With<Player>>,
     images: Res<Assets<Image>>,
     audio: Res<Audio>,
     time: Res<Time>,
-    actions: Res<Actions>,
     audio_assets: Res<AudioAssets>,
 ) {
-    if actions.player_movement.is_none() {
-        return;
-    }
+    for (mut player_transform, texture, mut player_movement, mut footstep_timer, actions) in &mut player_query {
+        // Keeps sliding for a bit after letting go or getting knocked back
+        player_movement.accelerate(actions.movement, time.delta_seconds());
 
-    for (mut player_transform, texture, player_movement, mut footstep_timer) in &mut player_query {
-        let movement = Vec3::new(
-            actions.player_movement.unwrap().x * player_movement.speed * time.delta_seconds(),
-            actions.player_movement.unwrap().y * player_movement.speed * time.delta_seconds(),
-            0.,
-        );
-
-        player_transform.translation += movement;
-
-        // Keep the player in the game area
-        let texture_size = images.get(texture).unwrap().texture_descriptor.size;
-
-        let player_size = Vec2::new(
-            texture_size.width as f32 * player_transform.scale.x.abs(),
-            texture_size.height as f32 as f32 * player_transform.scale.y.abs(),
-        );
-
-        let game_area = Vec2::new(
-            GAME_AREA_WIDTH - WALL_WIDTH * 2.0,
-            GAME_AREA_HEIGHT - WALL_HEIGHT * 2.0,
-        );
-
-        let bounding_box = Vec2::new(game_area.x - player_size.x, game_area.y - player_size.y);
+        player_transform.translation += (player_movement.velocity * time.delta_seconds()).extend(0.);
 
-        player_transform.translation.x = player_transform
-            .translation
-            .x
-            .clamp(-bounding_box.x / 2.0, bounding_box.x / 2.0);
+        keep_in_game_area(&mut player_transform, texture, &images);
 
-        player_transform.translation.y = player_transform
-            .translation
-            .y
-            .clamp(-bounding_box.y / 2.0, bounding_box.y / 2.0);
+        if actions.movement.is_none() {
+            continue;
+        }
 
         // Play the step sound
         if footstep_timer.tick(time.delta()).just_finished() {
@@ -130,111 +280,236 @@ fn move_player(
     }
 }
 
-fn aim_player(
+/// Keeps the player from going through the walls
+fn keep_in_game_area(player_transform: &mut Transform, texture: &Handle<Image>, images: &Assets<Image>) {
+    let texture_size = images.get(texture).unwrap().texture_descriptor.size;
+
+    let player_size = Vec2::new(
+        texture_size.width as f32 * player_transform.scale.x.abs(),
+        texture_size.height as f32 as f32 * player_transform.scale.y.abs(),
+    );
+
+    let game_area = Vec2::new(
+        GAME_AREA_WIDTH - WALL_WIDTH * 2.0,
+        GAME_AREA_HEIGHT - WALL_HEIGHT * 2.0,
+    );
+
+    let bounding_box = Vec2::new(game_area.x - player_size.x, game_area.y - player_size.y);
+
+    player_transform.translation.x = player_transform
+        .translation
+        .x
+        .clamp(-bounding_box.x / 2.0, bounding_box.x / 2.0);
+
+    player_transform.translation.y = player_transform
+        .translation
+        .y
+        .clamp(-bounding_box.y / 2.0, bounding_box.y / 2.0);
+}
+
+fn dash(
+    mut commands: Commands,
+    mut player_query: Query<(&mut Transform, &Handle<Image>, &Sprite, &Movement, &mut Dash, &mut PlayerActions), With<Player>>,
+    images: Res<Assets<Image>>,
     time: Res<Time>,
-    windows: Res<Windows>,
-    mut player_query: Query<(&mut Transform, &Rotation), With<Player>>,
-    camera_query: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
 ) {
-    let window = windows.primary();
-    let (camera, camera_transform) = camera_query.single();
+    for (mut player_transform, texture, sprite, movement, mut dash, mut actions) in &mut player_query {
+        dash.time.tick(time.delta());
+        dash.invulnerability.tick(time.delta());
 
-    let (mut player_transform, rotation) = player_query.single_mut();
-    let player_translation = player_transform.translation.xy();
+        // The charges come back one at a time
+        if dash.charges < dash.max_charges && dash.cooldown.tick(time.delta()).just_finished() {
+            dash.charges += 1;
 
-    if let Some(cursor_position) = window.cursor_position() {
-        let window_size = Vec2::new(window.width() as f32, window.height() as f32);
+            if dash.charges < dash.max_charges {
+                dash.cooldown.reset();
+            }
+        }
 
-        // get world cursor position
-        let ndc = (cursor_position / window_size) * 2.0 - Vec2::ONE;
-        let ndc_to_world = camera_transform.compute_matrix() * camera.projection_matrix().inverse();
-        let cursor_world_position = ndc_to_world.project_point3(ndc.extend(-1.0)).truncate();
+        // Stunned players can't dash
+        if std::mem::take(&mut actions.dash) && dash.charges > 0 && movement.current_speed() > 0. {
+            // Dash where the player is going, or forward when standing still
+            dash.direction = actions
+                .movement
+                .filter(|movement| movement.length_squared() > 0.)
+                .unwrap_or_else(|| player_transform.up().truncate())
+                .normalize();
+
+            // Start recharging unless already recharging another dash
+            if dash.charges == dash.max_charges {
+                dash.cooldown.reset();
+            }
 
-        // rotate player to cursor
-        let to_cursor = (cursor_world_position - player_translation).normalize();
-        let target_rotation = Quat::from_rotation_arc(Vec3::Y, to_cursor.extend(0.));
-        let target_rotation_z =
-            (Quat::from_rotation_arc(Vec3::Y, to_cursor.extend(0.)).z + 1.) / 2.;
+            dash.charges -= 1;
+            dash.time.reset();
+            dash.invulnerability.reset();
+            dash.afterimage_timer.reset();
+        }
 
-        // TODO: All of the code below can be optimized and simplified
+        if !dash.is_dashing() {
+            continue;
+        }
 
-        let player_rotation = (player_transform.rotation.z + 1.) / 2.;
-        let diff = player_rotation - target_rotation_z;
+        player_transform.translation += (dash.direction * DASH_SPEED * time.delta_seconds()).extend(0.);
+
+        keep_in_game_area(&mut player_transform, texture, &images);
+
+        if dash.afterimage_timer.tick(time.delta()).just_finished() {
+            let mut afterimage_transform = *player_transform;
+            afterimage_transform.translation.z -= 0.1;
+
+            commands
+                .spawn_bundle(SpriteBundle {
+                    sprite: Sprite {
+                        color: sprite.color.with_a(0.5),
+                        ..default()
+                    },
+                    texture: texture.clone(),
+                    transform: afterimage_transform,
+                    ..default()
+                })
+                .insert(Afterimage(Timer::from_seconds(AFTERIMAGE_LIFETIME, false)))
+                .insert(Name::new("Afterimage"));
+        }
+    }
+}
 
-        if diff.abs() < 0.001 {
-            player_transform.rotation = target_rotation;
-            return;
+fn fade_afterimages(
+    mut commands: Commands,
+    mut afterimages: Query<(Entity, &mut Sprite, &mut Afterimage)>,
+    time: Res<Time>,
+) {
+    for (entity, mut sprite, mut afterimage) in afterimages.iter_mut() {
+        if afterimage.tick(time.delta()).finished() {
+            commands.entity(entity).despawn_recursive();
+            continue;
         }
 
-        let rotation_amount = time.delta_seconds() * rotation.rotation_speed;
+        sprite.color.set_a(0.5 * (1. - afterimage.percent()));
+    }
+}
 
-        if diff > 0. {
-            if diff < 0.5 {
-                player_transform.rotate_z(-rotation_amount);
-            } else {
-                let old_rotation = player_transform.rotation;
-                player_transform.rotate_z(rotation_amount);
+fn drop_afterimages(mut commands: Commands, afterimages: Query<Entity, With<Afterimage>>) {
+    for afterimage in afterimages.iter() {
+        commands.entity(afterimage).despawn_recursive();
+    }
+}
 
-                if player_transform.rotation.z < old_rotation.z {
-                    player_transform.rotation = Quat::from_rotation_z(-PI);
-                }
-            }
-        } else {
-            if 1. + diff < 0.5 {
-                let old_rotation = player_transform.rotation;
-                player_transform.rotate_z(-rotation_amount);
-
-                if player_transform.rotation.z > old_rotation.z {
-                    player_transform.rotation = Quat::from_rotation_z(PI);
-                }
-            } else {
-                player_transform.rotate_z(rotation_amount);
+fn aim_player(
+    time: Res<Time>,
+    settings: Res<Settings>,
+    mut player_query: Query<(&mut Transform, &Rotation, &PlayerActions), With<Player>>,
+    enemies: Query<&Transform, (With<Enemy>, Without<Player>)>,
+) {
+    for (mut player_transform, rotation, actions) in &mut player_query {
+        let to_target = match actions.aim {
+            Some(to_target) => to_target,
+            None => continue,
+        };
+
+        let mut target_angle = Vec2::Y.angle_between(to_target);
+
+        // Pull sticks and touches towards the nearest enemy in front of the player
+        if actions.aim_assisted && settings.aim_assist > 0. {
+            let player_position = player_transform.translation.xy();
+
+            let nearest_enemy = enemies
+                .iter()
+                .map(|enemy| enemy.translation.xy() - player_position)
+                .filter(|to_enemy| {
+                    to_enemy.length() < AIM_ASSIST_RANGE
+                        && to_target.angle_between(*to_enemy).abs() < AIM_ASSIST_CONE.to_radians()
+                })
+                .min_by(|a, b| a.length_squared().total_cmp(&b.length_squared()));
+
+            if let Some(to_enemy) = nearest_enemy {
+                target_angle += to_target.angle_between(to_enemy) * settings.aim_assist;
             }
         }
+
+        // Turn the shorter way around
+        let current_angle = Vec2::Y.angle_between(player_transform.up().truncate());
+        let difference = (target_angle - current_angle + PI).rem_euclid(TAU) - PI;
+
+        let max_turn = rotation.rotation_speed * time.delta_seconds();
+
+        player_transform.rotation =
+            Quat::from_rotation_z(current_angle + difference.clamp(-max_turn, max_turn));
     }
 }
 
-fn check_if_dead(player: Query<&Health, With<Player>>, mut state: ResMut<State<GameState>>) {
-    let player = player.single();
-
-    if player.get_health() <= 0.0 {
+fn check_if_dead(
+    mut commands: Commands,
+    players: Query<(Entity, &Health), With<Player>>,
+    mut state: ResMut<State<GameState>>,
+) {
+    // A headless server has no players until someone joins
+    if !players.is_empty() && players.iter().all(|(_, health)| health.get_health() <= 0.0) {
         state.set(GameState::GameOver).unwrap();
+        return;
+    }
+
+    // In co-op the others keep playing without the fallen player
+    for (player, health) in players.iter() {
+        if health.get_health() <= 0.0 {
+            commands.entity(player).despawn_recursive();
+        }
     }
 }
 
-fn drop_player(mut commands: Commands, player: Query<Entity, With<Player>>) {
-    commands.entity(player.single()).despawn_recursive();
+fn drop_player(mut commands: Commands, players: Query<Entity, With<Player>>) {
+    for player in players.iter() {
+        commands.entity(player).despawn_recursive();
+    }
 }
 
 fn camera_follow(
-    player: Query<&Transform, With<Player>>,
-    mut camera: Query<&mut Transform, (With<Camera>, Without<Player>)>,
+    players: Query<&Transform, With<Player>>,
+    mut camera: Query<(&mut Transform, &mut OrthographicProjection), (With<MainCamera>, Without<Player>)>,
     windows: Res<Windows>,
+    time: Res<Time>,
 ) {
-    let player = player.single();
-    let mut camera_transform = camera.iter_mut().next().unwrap();
-    let window = windows.primary();
+    let (mut camera_transform, mut projection) = camera.single_mut();
+    let window_size = screen_size(&windows);
+
+    if players.is_empty() {
+        return;
+    }
+
+    // The area all of the players are in
+    let mut players_min = Vec2::splat(f32::MAX);
+    let mut players_max = Vec2::splat(f32::MIN);
+
+    for player in players.iter() {
+        players_min = players_min.min(player.translation.xy());
+        players_max = players_max.max(player.translation.xy());
+    }
+
+    let target = (players_min + players_max) / 2.;
+
+    // Zoom out so that everyone stays in view
+    let target_scale = ((players_max - players_min + Vec2::splat(CAMERA_ZOOM_MARGIN * 2.)) / window_size)
+        .max_element()
+        .clamp(1., CAMERA_ZOOM_MAX);
+
+    if (target_scale - projection.scale).abs() > 0.001 {
+        projection.scale += (target_scale - projection.scale) * (CAMERA_ZOOM_SPEED * time.delta_seconds()).min(1.);
+    }
 
     let bounding_box = Vec2::new(
-        GAME_AREA_WIDTH - window.width(),
-        GAME_AREA_HEIGHT - window.height(),
+        GAME_AREA_WIDTH - window_size.x * projection.scale,
+        GAME_AREA_HEIGHT - window_size.y * projection.scale,
     );
 
     camera_transform.translation.x = if bounding_box.x >= 0. {
-        player
-            .translation
-            .x
-            .clamp(-bounding_box.x / 2.0, bounding_box.x / 2.0)
+        target.x.clamp(-bounding_box.x / 2.0, bounding_box.x / 2.0)
     } else {
         // If the screen is wider than the play area, keep the camera centered
         0.
     };
 
     camera_transform.translation.y = if bounding_box.y >= 0. {
-        player
-            .translation
-            .y
-            .clamp(-bounding_box.y / 2.0, bounding_box.y / 2.0)
+        target.y.clamp(-bounding_box.y / 2.0, bounding_box.y / 2.0)
     } else {
         // If the screen is higher than the play area, keep the camera centered
         0.
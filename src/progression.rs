@@ -0,0 +1,423 @@
+use std::collections::HashSet;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{loading::FontAssets, score::Score, storage, GameState};
+
+pub struct ProgressionPlugin;
+
+/// This plugin turns the score of every run into credits that are kept between runs
+/// and draws the upgrade screen during the State `GameState::Upgrades`,
+/// where the credits buy the unlocks defined in `assets/unlocks.ron`.
+impl Plugin for ProgressionPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(load_unlocks())
+            .insert_resource(storage::load::<Progression>(PROGRESSION_KEY).unwrap_or_default())
+            .init_resource::<ButtonColors>()
+            .add_system_set(SystemSet::on_enter(GameState::GameOver).with_system(award_credits))
+            .add_system_set(SystemSet::on_enter(GameState::Upgrades).with_system(setup_upgrades))
+            .add_system_set(
+                SystemSet::on_update(GameState::Upgrades).with_system(click_upgrade_button),
+            )
+            .add_system_set(SystemSet::on_exit(GameState::Upgrades).with_system(cleanup_upgrades));
+    }
+}
+
+const PROGRESSION_KEY: &str = "progression";
+
+/// Score it takes to earn one credit
+pub const POINTS_PER_CREDIT: u32 = 100;
+
+pub const DEFAULT_MAX_HEALTH: f32 = 100.;
+pub const DEFAULT_SPEED: f32 = 80.;
+pub const DEFAULT_ROTATION_SPEED: f32 = 1.15;
+
+#[derive(Deserialize)]
+pub enum UnlockKind {
+    /// Lets the players switch to the weapon with this name
+    Weapon(String),
+    /// Starts every run with the perk with this name
+    StartingPerk(String),
+    /// Other starting stats for the players, one can be picked at a time
+    Variant {
+        max_health: f32,
+        speed: f32,
+        rotation_speed: f32,
+    },
+    /// Color of the lasers, one can be picked at a time
+    LaserColor(f32, f32, f32),
+}
+
+impl UnlockKind {
+    /// Whether it has to be picked on the upgrade screen after buying it
+    fn is_pickable(&self) -> bool {
+        matches!(
+            self,
+            UnlockKind::Variant { .. } | UnlockKind::LaserColor(..)
+        )
+    }
+}
+
+#[derive(Deserialize)]
+pub struct Unlock {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    /// Credits it takes to buy
+    pub cost: u32,
+    pub kind: UnlockKind,
+}
+
+/// The unlocks defined in `assets/unlocks.ron`
+#[derive(Deref)]
+pub struct Unlocks(pub Vec<Unlock>);
+
+/// Everything kept between runs
+#[derive(Serialize, Deserialize, Default)]
+pub struct Progression {
+    pub credits: u32,
+    /// Ids of the unlocks bought so far
+    pub unlocked: HashSet<String>,
+    /// Ids of the picked variant and laser color
+    pub variant: Option<String>,
+    pub laser_color: Option<String>,
+}
+
+/// What the players start a run with, made from the picked unlocks.
+/// Clients send theirs to the host when joining, see [crate::network]
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Loadout {
+    pub max_health: f32,
+    pub speed: f32,
+    pub rotation_speed: f32,
+    pub laser_color: Option<Color>,
+    /// Names of the weapons that weren't unlocked yet, weapons without an unlock can always be used
+    pub locked_weapons: Vec<String>,
+}
+
+impl Default for Loadout {
+    fn default() -> Self {
+        Self {
+            max_health: DEFAULT_MAX_HEALTH,
+            speed: DEFAULT_SPEED,
+            rotation_speed: DEFAULT_ROTATION_SPEED,
+            laser_color: None,
+            locked_weapons: Vec::new(),
+        }
+    }
+}
+
+impl Progression {
+    /// Names of the perks every run starts with
+    pub fn starting_perks<'a>(&'a self, unlocks: &'a Unlocks) -> impl Iterator<Item = &'a str> {
+        unlocks
+            .iter()
+            .filter(|unlock| self.unlocked.contains(&unlock.id))
+            .filter_map(|unlock| match &unlock.kind {
+                UnlockKind::StartingPerk(perk) => Some(perk.as_str()),
+                _ => None,
+            })
+    }
+
+    pub fn loadout(&self, unlocks: &Unlocks) -> Loadout {
+        let mut loadout = Loadout::default();
+
+        for unlock in unlocks.iter() {
+            if let UnlockKind::Weapon(weapon) = &unlock.kind {
+                if !self.unlocked.contains(&unlock.id) {
+                    loadout.locked_weapons.push(weapon.clone());
+                }
+            }
+
+            let picked = self.variant.as_ref() == Some(&unlock.id)
+                || self.laser_color.as_ref() == Some(&unlock.id);
+
+            if !picked || !self.unlocked.contains(&unlock.id) {
+                continue;
+            }
+
+            match unlock.kind {
+                UnlockKind::Variant {
+                    max_health,
+                    speed,
+                    rotation_speed,
+                } => {
+                    loadout.max_health = max_health;
+                    loadout.speed = speed;
+                    loadout.rotation_speed = rotation_speed;
+                }
+                UnlockKind::LaserColor(r, g, b) => {
+                    loadout.laser_color = Some(Color::rgb(r, g, b));
+                }
+                _ => {}
+            }
+        }
+
+        loadout
+    }
+
+    /// The slot of the unlock the given one takes the place of when picked
+    fn pick_slot(&mut self, kind: &UnlockKind) -> Option<&mut Option<String>> {
+        match kind {
+            UnlockKind::Variant { .. } => Some(&mut self.variant),
+            UnlockKind::LaserColor(..) => Some(&mut self.laser_color),
+            _ => None,
+        }
+    }
+
+    /// Buys the unlock, or picks and unpicks it once bought
+    fn select(&mut self, unlock: &Unlock) {
+        if !self.unlocked.contains(&unlock.id) {
+            if self.credits < unlock.cost {
+                return;
+            }
+
+            self.credits -= unlock.cost;
+            self.unlocked.insert(unlock.id.clone());
+
+            // Bought to be used right away
+            if let Some(slot) = self.pick_slot(&unlock.kind) {
+                *slot = Some(unlock.id.clone());
+            }
+        } else if let Some(slot) = self.pick_slot(&unlock.kind) {
+            *slot = if slot.as_ref() == Some(&unlock.id) {
+                None
+            } else {
+                Some(unlock.id.clone())
+            };
+        }
+
+        storage::save(PROGRESSION_KEY, &*self);
+    }
+
+    fn label(&self, unlock: &Unlock) -> String {
+        let status = if !self.unlocked.contains(&unlock.id) {
+            format!("{} credits", unlock.cost)
+        } else if !unlock.kind.is_pickable() {
+            "Owned".to_string()
+        } else if self.variant.as_ref() == Some(&unlock.id)
+            || self.laser_color.as_ref() == Some(&unlock.id)
+        {
+            "Picked".to_string()
+        } else {
+            "Owned, click to pick".to_string()
+        };
+
+        format!("{} ({})", unlock.name, status)
+    }
+}
+
+#[derive(Component)]
+struct UpgradeScreen;
+
+#[derive(Component, Clone, Copy)]
+enum UpgradeButton {
+    /// Buys or picks the unlock with this index in [Unlocks]
+    Unlock(usize),
+    Back,
+}
+
+#[derive(Component)]
+struct CreditsText;
+
+struct ButtonColors {
+    normal: UiColor,
+    hovered: UiColor,
+}
+
+impl Default for ButtonColors {
+    fn default() -> Self {
+        ButtonColors {
+            normal: Color::rgb(0.15, 0.15, 0.15).into(),
+            hovered: Color::rgb(0.25, 0.25, 0.25).into(),
+        }
+    }
+}
+
+fn load_unlocks() -> Unlocks {
+    Unlocks(
+        ron::from_str(include_str!("../assets/unlocks.ron"))
+            .expect("Failed to parse the unlock definitions"),
+    )
+}
+
+fn award_credits(mut progression: ResMut<Progression>, score: Res<Score>) {
+    progression.credits += score.0 / POINTS_PER_CREDIT;
+
+    storage::save(PROGRESSION_KEY, &*progression);
+}
+
+fn setup_upgrades(
+    mut commands: Commands,
+    font_assets: Res<FontAssets>,
+    button_colors: Res<ButtonColors>,
+    progression: Res<Progression>,
+    unlocks: Res<Unlocks>,
+) {
+    let text_style = |font_size: f32, color: Color| TextStyle {
+        font: font_assets.fira_sans.clone(),
+        font_size,
+        color,
+    };
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                flex_direction: FlexDirection::ColumnReverse,
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            color: Color::NONE.into(),
+            ..default()
+        })
+        .with_children(|parent| {
+            parent.spawn_bundle(TextBundle::from_section(
+                "Upgrades",
+                text_style(40.0, Color::WHITE),
+            ));
+
+            parent
+                .spawn_bundle(
+                    TextBundle::from_section(
+                        format!("Credits: {}", progression.credits),
+                        text_style(24.0, Color::GOLD),
+                    )
+                    .with_style(Style {
+                        margin: UiRect {
+                            bottom: Val::Percent(2.),
+                            ..default()
+                        },
+                        ..default()
+                    }),
+                )
+                .insert(CreditsText);
+
+            // Two columns, so all of the unlocks fit on the screen of the web build
+            parent
+                .spawn_bundle(NodeBundle {
+                    style: Style {
+                        size: Size::new(Val::Px(860.0), Val::Auto),
+                        flex_wrap: FlexWrap::WrapReverse,
+                        justify_content: JustifyContent::Center,
+                        ..default()
+                    },
+                    color: Color::NONE.into(),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    for (index, unlock) in unlocks.iter().enumerate() {
+                        parent
+                            .spawn_bundle(ButtonBundle {
+                                style: Style {
+                                    size: Size::new(Val::Px(420.0), Val::Px(40.0)),
+                                    margin: UiRect::all(Val::Px(3.)),
+                                    flex_direction: FlexDirection::ColumnReverse,
+                                    justify_content: JustifyContent::Center,
+                                    align_items: AlignItems::Center,
+                                    ..default()
+                                },
+                                color: button_colors.normal,
+                                ..default()
+                            })
+                            .with_children(|parent| {
+                                parent.spawn_bundle(TextBundle::from_section(
+                                    progression.label(unlock),
+                                    text_style(18.0, Color::rgb(0.9, 0.9, 0.9)),
+                                ));
+
+                                parent.spawn_bundle(TextBundle::from_section(
+                                    unlock.description.clone(),
+                                    text_style(12.0, Color::rgb(0.6, 0.6, 0.6)),
+                                ));
+                            })
+                            .insert(UpgradeButton::Unlock(index));
+                    }
+                });
+
+            parent
+                .spawn_bundle(ButtonBundle {
+                    style: Style {
+                        size: Size::new(Val::Px(100.0), Val::Px(40.0)),
+                        margin: UiRect {
+                            top: Val::Percent(2.),
+                            ..default()
+                        },
+                        justify_content: JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        ..default()
+                    },
+                    color: button_colors.normal,
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent.spawn_bundle(TextBundle::from_section(
+                        "Back",
+                        text_style(32.0, Color::rgb(0.9, 0.9, 0.9)),
+                    ));
+                })
+                .insert(UpgradeButton::Back);
+        })
+        .insert(UpgradeScreen);
+}
+
+fn click_upgrade_button(
+    button_colors: Res<ButtonColors>,
+    mut state: ResMut<State<GameState>>,
+    mut progression: ResMut<Progression>,
+    unlocks: Res<Unlocks>,
+    mut interaction_query: Query<
+        (&Interaction, &mut UiColor, &UpgradeButton),
+        (Changed<Interaction>, With<Button>),
+    >,
+    buttons: Query<(&UpgradeButton, &Children)>,
+    mut texts: Query<&mut Text, Without<CreditsText>>,
+    mut credits_text: Query<&mut Text, With<CreditsText>>,
+) {
+    for (interaction, mut color, button) in &mut interaction_query {
+        match *interaction {
+            Interaction::Clicked => {
+                let index = match *button {
+                    UpgradeButton::Unlock(index) => index,
+                    UpgradeButton::Back => {
+                        state.set(GameState::Menu).unwrap();
+                        continue;
+                    }
+                };
+
+                progression.select(&unlocks[index]);
+
+                // Picking one unlock can unpick another, so all of the labels are updated
+                for (button, children) in buttons.iter() {
+                    let index = match *button {
+                        UpgradeButton::Unlock(index) => index,
+                        UpgradeButton::Back => continue,
+                    };
+
+                    if let Some(mut text) = children
+                        .first()
+                        .and_then(|child| texts.get_mut(*child).ok())
+                    {
+                        text.sections[0].value = progression.label(&unlocks[index]);
+                    }
+                }
+
+                for mut text in credits_text.iter_mut() {
+                    text.sections[0].value = format!("Credits: {}", progression.credits);
+                }
+            }
+            Interaction::Hovered => {
+                *color = button_colors.hovered;
+            }
+            Interaction::None => {
+                *color = button_colors.normal;
+            }
+        }
+    }
+}
+
+fn cleanup_upgrades(mut commands: Commands, screen: Query<Entity, With<UpgradeScreen>>) {
+    commands.entity(screen.single()).despawn_recursive();
+}
@@ -0,0 +1,151 @@
+use crate::{
+    character::{Health, Movement},
+    network::NetworkRole,
+    player::{Dash, Player, PlayerDamagedEvent},
+    weapon::EntityHitEvent,
+    GameState, GAME_AREA_HEIGHT, GAME_AREA_WIDTH, WALL_HEIGHT, WALL_WIDTH,
+};
+use bevy::{math::Vec3Swizzles, prelude::*};
+use bevy_rapier2d::prelude::*;
+
+pub struct ProjectilePlugin;
+
+impl Plugin for ProjectilePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::on_update(GameState::Playing)
+                .with_system(move_projectiles)
+                .with_system(hit_players)
+                .with_system(destroy_shot_projectiles),
+        )
+        .add_system_set(SystemSet::on_exit(GameState::Playing).with_system(drop_projectiles));
+    }
+}
+
+pub const PROJECTILE_RADIUS: f32 = 6.;
+pub const PROJECTILE_LIFETIME: f32 = 5.;
+pub const PROJECTILE_KNOCKBACK: f32 = 120.;
+
+/// Fired by ranged enemies, hurts the first player it touches and can be shot down with the laser
+#[derive(Component)]
+pub struct Projectile {
+    pub velocity: Vec2,
+    pub damage: f32,
+    lifetime: Timer,
+}
+
+#[derive(Bundle)]
+pub struct ProjectileBundle {
+    projectile: Projectile,
+    collider: Collider,
+    sensor: Sensor,
+    name: Name,
+    #[bundle]
+    sprite: SpriteBundle,
+}
+
+impl ProjectileBundle {
+    pub fn new(position: Vec2, velocity: Vec2, damage: f32) -> Self {
+        Self {
+            projectile: Projectile {
+                velocity,
+                damage,
+                lifetime: Timer::from_seconds(PROJECTILE_LIFETIME, false),
+            },
+            collider: Collider::ball(PROJECTILE_RADIUS),
+            sensor: Sensor,
+            name: Name::new("Projectile"),
+            sprite: SpriteBundle {
+                sprite: Sprite {
+                    color: Color::rgb(0.6, 1., 0.3),
+                    custom_size: Some(Vec2::splat(PROJECTILE_RADIUS * 2.)),
+                    ..default()
+                },
+                transform: Transform::from_translation(position.extend(2.5)),
+                ..default()
+            },
+        }
+    }
+}
+
+fn move_projectiles(
+    mut commands: Commands,
+    mut projectiles: Query<(Entity, &mut Transform, &mut Projectile)>,
+    time: Res<Time>,
+    network_role: Res<NetworkRole>,
+) {
+    for (entity, mut transform, mut projectile) in projectiles.iter_mut() {
+        transform.translation += (projectile.velocity * time.delta_seconds()).extend(0.);
+
+        // Clients get rid of their projectiles when the host does
+        if !network_role.is_authority() {
+            continue;
+        }
+
+        let position = transform.translation.xy();
+
+        let hit_wall = position.x.abs() > (GAME_AREA_WIDTH / 2. - WALL_WIDTH)
+            || position.y.abs() > (GAME_AREA_HEIGHT / 2. - WALL_HEIGHT);
+
+        if projectile.lifetime.tick(time.delta()).finished() || hit_wall {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+fn hit_players(
+    mut commands: Commands,
+    projectiles: Query<(Entity, &Transform, &Projectile)>,
+    mut players: Query<(&Transform, &mut Health, &Dash, &mut Movement), With<Player>>,
+    mut player_damaged_events: EventWriter<PlayerDamagedEvent>,
+    network_role: Res<NetworkRole>,
+) {
+    if !network_role.is_authority() {
+        return;
+    }
+
+    for (entity, transform, projectile) in projectiles.iter() {
+        let position = transform.translation.xy();
+
+        // Projectiles fly through dashing players
+        let player = players.iter_mut().find(|(player_transform, _, dash, _)| {
+            player_transform.translation.xy().distance(position) < PROJECTILE_RADIUS + 16.
+                && !dash.is_invulnerable()
+        });
+
+        if let Some((_, mut player_health, _, mut player_movement)) = player {
+            player_health.take_damage(projectile.damage);
+            player_damaged_events.send(PlayerDamagedEvent {
+                amount: projectile.damage,
+            });
+
+            player_movement
+                .knock_back(projectile.velocity.normalize_or_zero() * PROJECTILE_KNOCKBACK);
+
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+fn destroy_shot_projectiles(
+    mut commands: Commands,
+    mut entity_hit_events: EventReader<EntityHitEvent>,
+    projectiles: Query<(), With<Projectile>>,
+    network_role: Res<NetworkRole>,
+) {
+    if !network_role.is_authority() {
+        return;
+    }
+
+    for event in entity_hit_events.iter() {
+        if projectiles.get(event.entity).is_ok() {
+            commands.entity(event.entity).despawn_recursive();
+        }
+    }
+}
+
+fn drop_projectiles(mut commands: Commands, projectiles: Query<Entity, With<Projectile>>) {
+    for projectile in projectiles.iter() {
+        commands.entity(projectile).despawn_recursive();
+    }
+}
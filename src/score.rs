@@ -1,19 +1,201 @@
-use bevy::prelude::*;
+use std::collections::BTreeMap;
 
-use crate::GameState;
+use bevy::{math::Vec3Swizzles, prelude::*};
+
+use crate::{
+    character::Health,
+    enemy::{EnemyDamagedEvent, ENEMY_KILL_SCORE},
+    player::{Player, PlayerDamagedEvent},
+    weapon::{Weapon, WeaponStatus},
+    GameState,
+};
 
 pub struct ScorePlugin;
 
 impl Plugin for ScorePlugin {
     fn build(&self, app: &mut App) {
-        app.insert_resource(Score(0))
-            .add_system_set(SystemSet::on_enter(GameState::Playing).with_system(reset_score));
+        app.add_event::<ScoreEvent>()
+            .insert_resource(Score(0))
+            .insert_resource(ScoreBreakdown::default())
+            .insert_resource(ScoreMultiplier(1))
+            .insert_resource(ComboTimer(Timer::from_seconds(COMBO_WINDOW, false)))
+            .insert_resource(BeamBurstKills(0))
+            .add_system_set(SystemSet::on_enter(GameState::Playing).with_system(reset_score))
+            .add_system_set(
+                SystemSet::on_update(GameState::Playing)
+                    .with_system(score_kills.label("score_kills"))
+                    .with_system(add_score.after("score_kills"))
+                    .with_system(decay_combo.after("score_kills"))
+                    .with_system(break_combo.after("score_kills")),
+            );
     }
 }
 
+/// Kills closer together than this raise the multiplier
+pub const COMBO_WINDOW: f32 = 3.;
+pub const SCORE_MULTIPLIER_MAX: u32 = 8;
+
+/// Bonus for every kill after the first one during a single beam
+pub const MULTI_KILL_BONUS: u32 = 50;
+/// Bonus for killing while the health of the player is below [LOW_HEALTH_THRESHOLD]
+pub const LOW_HEALTH_BONUS: u32 = 50;
+pub const LOW_HEALTH_THRESHOLD: f32 = 0.25;
+/// Bonus for killing an enemy further away than [LONG_RANGE_DISTANCE]
+pub const LONG_RANGE_BONUS: u32 = 50;
+pub const LONG_RANGE_DISTANCE: f32 = 400.;
+
 #[derive(Deref, DerefMut)]
 pub struct Score(pub u32);
 
-fn reset_score(mut score: ResMut<Score>) {
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum ScoreCategory {
+    Kill,
+    MultiKill,
+    LowHealth,
+    LongRange,
+    Boss,
+    Repair,
+}
+
+impl ScoreCategory {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ScoreCategory::Kill => "Kills",
+            ScoreCategory::MultiKill => "Multi-kills",
+            ScoreCategory::LowHealth => "Low health kills",
+            ScoreCategory::LongRange => "Long range kills",
+            ScoreCategory::Boss => "Bosses",
+            ScoreCategory::Repair => "Repairs",
+        }
+    }
+}
+
+/// Points gained during the current run, already multiplied by the [ScoreMultiplier]
+pub struct ScoreEvent {
+    pub category: ScoreCategory,
+    pub points: u32,
+    pub position: Vec2,
+}
+
+/// Points gained during the current run per [ScoreCategory]
+#[derive(Default, Deref, DerefMut)]
+pub struct ScoreBreakdown(pub BTreeMap<ScoreCategory, u32>);
+
+/// Multiplies the score gained from kills, raised by killing enemies in quick succession
+#[derive(Deref, DerefMut)]
+pub struct ScoreMultiplier(pub u32);
+
+/// The multiplier goes down by one every time this timer runs out
+#[derive(Deref, DerefMut)]
+struct ComboTimer(Timer);
+
+/// Enemies killed during the current beam
+#[derive(Deref, DerefMut)]
+pub struct BeamBurstKills(pub u32);
+
+fn reset_score(
+    mut score: ResMut<Score>,
+    mut score_breakdown: ResMut<ScoreBreakdown>,
+    mut score_multiplier: ResMut<ScoreMultiplier>,
+    mut combo_timer: ResMut<ComboTimer>,
+    mut beam_burst_kills: ResMut<BeamBurstKills>,
+) {
     score.0 = 0;
+    score_breakdown.clear();
+    score_multiplier.0 = 1;
+    combo_timer.reset();
+    beam_burst_kills.0 = 0;
+}
+
+fn score_kills(
+    mut enemy_damaged_events: EventReader<EnemyDamagedEvent>,
+    mut score_events: EventWriter<ScoreEvent>,
+    mut score_multiplier: ResMut<ScoreMultiplier>,
+    mut combo_timer: ResMut<ComboTimer>,
+    mut beam_burst_kills: ResMut<BeamBurstKills>,
+    players: Query<(&Transform, &Health), With<Player>>,
+    weapons: Query<(&Weapon, &Parent)>,
+) {
+    if weapons
+        .iter()
+        .all(|(weapon, _)| weapon.status == WeaponStatus::Idle)
+    {
+        beam_burst_kills.0 = 0;
+    }
+
+    for event in enemy_damaged_events.iter().filter(|event| event.killed) {
+        // The bonuses go by the player whose weapon made the kill
+        let killer = weapons
+            .get(event.source.entity)
+            .ok()
+            .and_then(|(_, parent)| players.get(parent.get()).ok());
+
+        let mut score = |category: ScoreCategory, points: u32| {
+            score_events.send(ScoreEvent {
+                category,
+                points: points * score_multiplier.0,
+                position: event.position,
+            });
+        };
+
+        score(ScoreCategory::Kill, ENEMY_KILL_SCORE);
+
+        beam_burst_kills.0 += 1;
+
+        if beam_burst_kills.0 > 1 {
+            score(
+                ScoreCategory::MultiKill,
+                MULTI_KILL_BONUS * (beam_burst_kills.0 - 1),
+            );
+        }
+
+        if let Some((player_transform, player_health)) = killer {
+            if player_health.get_health() / player_health.get_max_health() < LOW_HEALTH_THRESHOLD {
+                score(ScoreCategory::LowHealth, LOW_HEALTH_BONUS);
+            }
+
+            if player_transform.translation.xy().distance(event.position) > LONG_RANGE_DISTANCE {
+                score(ScoreCategory::LongRange, LONG_RANGE_BONUS);
+            }
+        }
+
+        // Every kill raises the multiplier, it goes back down when the combo timer runs out
+        score_multiplier.0 = (score_multiplier.0 + 1).min(SCORE_MULTIPLIER_MAX);
+        combo_timer.reset();
+    }
+}
+
+fn add_score(
+    mut score_events: EventReader<ScoreEvent>,
+    mut score: ResMut<Score>,
+    mut score_breakdown: ResMut<ScoreBreakdown>,
+) {
+    for event in score_events.iter() {
+        score.0 += event.points;
+        *score_breakdown.entry(event.category).or_insert(0) += event.points;
+    }
+}
+
+fn decay_combo(
+    mut score_multiplier: ResMut<ScoreMultiplier>,
+    mut combo_timer: ResMut<ComboTimer>,
+    time: Res<Time>,
+) {
+    if combo_timer.tick(time.delta()).just_finished() && score_multiplier.0 > 1 {
+        score_multiplier.0 -= 1;
+
+        // Keep decaying until the multiplier is back to 1
+        if score_multiplier.0 > 1 {
+            combo_timer.reset();
+        }
+    }
+}
+
+fn break_combo(
+    mut player_damaged_events: EventReader<PlayerDamagedEvent>,
+    mut score_multiplier: ResMut<ScoreMultiplier>,
+) {
+    if player_damaged_events.iter().count() > 0 {
+        score_multiplier.0 = 1;
+    }
 }
@@ -0,0 +1,54 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::storage;
+
+pub struct SettingsPlugin;
+
+/// This plugin loads the settings saved in an earlier session, they are changed from the menu
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(storage::load::<Settings>(SETTINGS_KEY).unwrap_or_default());
+    }
+}
+
+const SETTINGS_KEY: &str = "settings";
+
+/// The aim assist strengths the menu goes through
+pub const AIM_ASSIST_LEVELS: [(f32, &str); 3] = [(0., "Off"), (0.35, "Low"), (0.7, "High")];
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Settings {
+    /// How much the aim of sticks and touches is pulled towards enemies, from 0 to 1
+    pub aim_assist: f32,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            aim_assist: AIM_ASSIST_LEVELS[1].0,
+        }
+    }
+}
+
+impl Settings {
+    pub fn aim_assist_name(&self) -> &'static str {
+        AIM_ASSIST_LEVELS
+            .iter()
+            .find(|(level, _)| *level == self.aim_assist)
+            .map(|(_, name)| *name)
+            .unwrap_or("Custom")
+    }
+
+    /// Goes to the next of the [AIM_ASSIST_LEVELS] and saves the settings
+    pub fn cycle_aim_assist(&mut self) {
+        let current = AIM_ASSIST_LEVELS
+            .iter()
+            .position(|(level, _)| *level == self.aim_assist);
+
+        let next = current.map_or(0, |current| (current + 1) % AIM_ASSIST_LEVELS.len());
+        self.aim_assist = AIM_ASSIST_LEVELS[next].0;
+
+        storage::save(SETTINGS_KEY, self);
+    }
+}
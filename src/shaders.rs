@@ -1,13 +1,15 @@
 use bevy::{prelude::*, sprite::Material2dPlugin};
 
-use self::pixelise::PixeliseMaterial;
+use self::{pixelise::PixeliseMaterial, shadowed_light::ShadowedLightMaterial};
 
 pub mod pixelise;
+pub mod shadowed_light;
 
 pub struct ShaderPlugin;
 
 impl Plugin for ShaderPlugin {
     fn build(&self, app: &mut App) {
-        app.add_plugin(Material2dPlugin::<PixeliseMaterial>::default());
+        app.add_plugin(Material2dPlugin::<PixeliseMaterial>::default())
+            .add_plugin(Material2dPlugin::<ShadowedLightMaterial>::default());
     }
 }
@@ -11,6 +11,9 @@ pub struct PixeliseMaterial {
     #[texture(0)]
     #[sampler(1)]
     pub source_image: Handle<Image>,
+    #[texture(2)]
+    #[sampler(3)]
+    pub light_map: Handle<Image>,
 }
 
 impl Material2d for PixeliseMaterial {
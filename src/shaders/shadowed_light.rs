@@ -0,0 +1,45 @@
+use bevy::{
+    prelude::*,
+    reflect::TypeUuid,
+    render::render_resource::{AsBindGroup, ShaderRef},
+    sprite::Material2d,
+};
+
+/// How many occluder edges a single light can be blocked by
+pub const MAX_SHADOW_EDGES: usize = 64;
+
+/// A point light that leaves out the parts of itself hidden behind the
+/// given edges, so its shadows never darken the other lights in the light map
+#[derive(AsBindGroup, TypeUuid, Clone)]
+#[uuid = "4b7c2e1a-93d5-4f0e-8a61-d2c5e7f90b38"]
+pub struct ShadowedLightMaterial {
+    #[uniform(0)]
+    pub color: Color,
+    #[uniform(0)]
+    pub position: Vec2,
+    #[uniform(0)]
+    pub radius: f32,
+    #[uniform(0)]
+    pub edge_count: u32,
+    /// Each edge is stored as `(start.x, start.y, end.x, end.y)` in world space
+    #[uniform(0)]
+    pub edges: [Vec4; MAX_SHADOW_EDGES],
+}
+
+impl Default for ShadowedLightMaterial {
+    fn default() -> Self {
+        Self {
+            color: Color::WHITE,
+            position: Vec2::ZERO,
+            radius: 0.,
+            edge_count: 0,
+            edges: [Vec4::ZERO; MAX_SHADOW_EDGES],
+        }
+    }
+}
+
+impl Material2d for ShadowedLightMaterial {
+    fn fragment_shader() -> ShaderRef {
+        "shaders/shadowed_light.wgsl".into()
+    }
+}
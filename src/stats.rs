@@ -0,0 +1,133 @@
+use std::{collections::HashMap, time::Duration};
+
+use bevy::{math::Vec3Swizzles, prelude::*};
+
+use crate::{
+    enemy::{Enemy, EnemyDamagedEvent, WeakPoint},
+    player::{Player, PlayerDamagedEvent},
+    weapon::{EntityHitEvent, Weapon, WeaponStatus},
+    GameState,
+};
+
+pub struct StatsPlugin;
+
+/// This plugin collects the statistics of the current run, shown on the game over screen
+impl Plugin for StatsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RunStats>()
+            .add_system_set(SystemSet::on_enter(GameState::Playing).with_system(reset_stats))
+            .add_system_set(
+                SystemSet::on_update(GameState::Playing)
+                    .with_system(track_time)
+                    .with_system(track_damage)
+                    .with_system(track_beam_accuracy)
+                    .with_system(track_distance_walked)
+                    .with_system(track_peak_enemy_count),
+            );
+    }
+}
+
+#[derive(Default)]
+pub struct RunStats {
+    pub time_survived: Duration,
+    pub enemies_killed: u32,
+    pub damage_dealt: f32,
+    pub damage_taken: f32,
+    /// Time spent beaming
+    pub beam_uptime: Duration,
+    /// Frames in which the beam hit an enemy
+    pub beam_enemy_hits: u32,
+    /// Frames in which the beam hit something other than an enemy
+    pub beam_missed_hits: u32,
+    pub distance_walked: f32,
+    pub peak_enemy_count: usize,
+    last_player_positions: HashMap<Entity, Vec2>,
+}
+
+impl RunStats {
+    /// Share of the beam time spent on enemies, between 0 and 1
+    pub fn beam_accuracy(&self) -> f32 {
+        let beam_hits = self.beam_enemy_hits + self.beam_missed_hits;
+
+        if beam_hits == 0 {
+            return 0.;
+        }
+
+        self.beam_enemy_hits as f32 / beam_hits as f32
+    }
+}
+
+fn reset_stats(mut stats: ResMut<RunStats>) {
+    *stats = RunStats::default();
+}
+
+fn track_time(mut stats: ResMut<RunStats>, weapons: Query<&Weapon>, time: Res<Time>) {
+    stats.time_survived += time.delta();
+
+    if weapons
+        .iter()
+        .any(|weapon| weapon.status == WeaponStatus::Beaming)
+    {
+        stats.beam_uptime += time.delta();
+    }
+}
+
+fn track_damage(
+    mut stats: ResMut<RunStats>,
+    mut enemy_damaged_events: EventReader<EnemyDamagedEvent>,
+    mut player_damaged_events: EventReader<PlayerDamagedEvent>,
+) {
+    for event in enemy_damaged_events.iter() {
+        stats.damage_dealt += event.amount;
+
+        if event.killed {
+            stats.enemies_killed += 1;
+        }
+    }
+
+    for event in player_damaged_events.iter() {
+        stats.damage_taken += event.amount;
+    }
+}
+
+fn track_beam_accuracy(
+    mut stats: ResMut<RunStats>,
+    mut entity_hit_events: EventReader<EntityHitEvent>,
+    enemies: Query<(), Or<(With<Enemy>, With<WeakPoint>)>>,
+) {
+    let mut hit_anything = false;
+    let mut hit_enemy = false;
+
+    // Burning enemies and explosions aren't hit by the beam
+    for event in entity_hit_events
+        .iter()
+        .filter(|event| event.source.kind.from_beam())
+    {
+        hit_anything = true;
+        hit_enemy |= enemies.get(event.entity).is_ok();
+    }
+
+    if hit_enemy {
+        stats.beam_enemy_hits += 1;
+    } else if hit_anything {
+        stats.beam_missed_hits += 1;
+    }
+}
+
+/// Adds up the distance walked by all of the players
+fn track_distance_walked(
+    mut stats: ResMut<RunStats>,
+    players: Query<(Entity, &Transform), With<Player>>,
+) {
+    for (player, transform) in players.iter() {
+        let position = transform.translation.xy();
+
+        if let Some(last_position) = stats.last_player_positions.insert(player, position) {
+            stats.distance_walked += position.distance(last_position);
+        }
+    }
+}
+
+fn track_peak_enemy_count(mut stats: ResMut<RunStats>, enemies: Query<(), With<Enemy>>) {
+    stats.peak_enemy_count = stats.peak_enemy_count.max(enemies.iter().count());
+}
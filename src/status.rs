@@ -0,0 +1,429 @@
+use crate::{
+    character::{DamageKind, DamageSource, Health, Movement},
+    enemy::{Enemy, EnemyDamagedEvent, WeakPoint},
+    network::NetworkRole,
+    player::{Player, PlayerDamagedEvent},
+    weapon::{EntityHitEvent, Weapon},
+    GameState,
+};
+use bevy::{math::Vec3Swizzles, prelude::*};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+pub struct StatusPlugin;
+
+/// This plugin handles timed effects like slows and burns on anything with [StatusEffects].
+/// Lasers put the effect of their weapon on whatever they hit and killed enemies sometimes drop pickups with buffs.
+/// In a networked game the host decides on the effects and pickups, the clients get them in the snapshots.
+impl Plugin for StatusPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::on_update(GameState::Playing)
+                .with_system(apply_weapon_effects)
+                .with_system(tick_status_effects)
+                .with_system(burn)
+                .with_system(tint_status_effects)
+                .with_system(drop_pickups)
+                .with_system(collect_pickups),
+        )
+        .add_system_set(SystemSet::on_exit(GameState::Playing).with_system(clean_pickups));
+    }
+}
+
+/// The same effect can't be stacked more times than this
+pub const MAX_STATUS_STACKS: usize = 3;
+
+/// Chance of a killed enemy dropping a pickup
+pub const PICKUP_DROP_CHANCE: f64 = 0.1;
+pub const PICKUP_LIFETIME: f32 = 10.;
+pub const PICKUP_SIZE: f32 = 14.;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum StatusKind {
+    /// Lowers the speed by `strength` as a fraction of it
+    Slow,
+    /// Deals `strength` damage per second
+    Burn,
+    /// Can't move or attack
+    Stun,
+    /// Blocks `strength` as a fraction of the damage
+    Shield,
+    /// Raises the speed by `strength` as a fraction of it
+    Haste,
+}
+
+impl StatusKind {
+    pub fn color(&self) -> Color {
+        match self {
+            StatusKind::Slow => Color::rgb(0.5, 0.7, 1.),
+            StatusKind::Burn => Color::rgb(1., 0.5, 0.2),
+            StatusKind::Stun => Color::rgb(1., 1., 0.4),
+            StatusKind::Shield => Color::rgb(0.4, 1., 1.),
+            StatusKind::Haste => Color::rgb(0.5, 1., 0.5),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct StatusEffectDefinition {
+    pub kind: StatusKind,
+    pub strength: f32,
+    /// Seconds the effect lasts
+    pub duration: f32,
+}
+
+struct StatusEffect {
+    kind: StatusKind,
+    strength: f32,
+    timer: Timer,
+    source: Option<DamageSource>,
+}
+
+#[derive(Component, Default)]
+pub struct StatusEffects(Vec<StatusEffect>);
+
+/// An effect as the host sends it to the clients, see [crate::network]
+#[derive(Serialize, Deserialize)]
+pub struct StatusEffectState {
+    pub kind: StatusKind,
+    pub strength: f32,
+    /// Seconds until it runs out
+    pub time_left: f32,
+}
+
+impl StatusEffects {
+    /// Effects from different sources stack up to [MAX_STATUS_STACKS] times,
+    /// applying an effect from the same source again only refreshes it
+    pub fn apply(&mut self, definition: &StatusEffectDefinition, source: Option<DamageSource>) {
+        let source_entity = source.map(|source| source.entity);
+
+        if let Some(effect) = self.0.iter_mut().find(|effect| {
+            effect.kind == definition.kind
+                && effect.source.map(|source| source.entity) == source_entity
+        }) {
+            effect.strength = effect.strength.max(definition.strength);
+            effect.timer = Timer::from_seconds(definition.duration, false);
+            return;
+        }
+
+        let stacks = self
+            .0
+            .iter()
+            .filter(|effect| effect.kind == definition.kind)
+            .count();
+
+        // Make room by dropping the stack closest to running out
+        if stacks >= MAX_STATUS_STACKS {
+            let oldest = self
+                .0
+                .iter()
+                .enumerate()
+                .filter(|(_, effect)| effect.kind == definition.kind)
+                .min_by(|(_, a), (_, b)| time_left(&a.timer).total_cmp(&time_left(&b.timer)))
+                .map(|(index, _)| index);
+
+            if let Some(index) = oldest {
+                self.0.remove(index);
+            }
+        }
+
+        self.0.push(StatusEffect {
+            kind: definition.kind,
+            strength: definition.strength,
+            timer: Timer::from_seconds(definition.duration, false),
+            source,
+        });
+    }
+
+    pub fn states(&self) -> Vec<StatusEffectState> {
+        self.0
+            .iter()
+            .map(|effect| StatusEffectState {
+                kind: effect.kind,
+                strength: effect.strength,
+                time_left: time_left(&effect.timer),
+            })
+            .collect()
+    }
+
+    /// Swaps the effects for the ones on the host, their sources are only known there
+    pub fn set_states(&mut self, states: &[StatusEffectState]) {
+        self.0 = states
+            .iter()
+            .map(|state| StatusEffect {
+                kind: state.kind,
+                strength: state.strength,
+                timer: Timer::from_seconds(state.time_left, false),
+                source: None,
+            })
+            .collect();
+    }
+
+    pub fn has(&self, kind: StatusKind) -> bool {
+        self.0.iter().any(|effect| effect.kind == kind)
+    }
+
+    fn speed_modifier(&self) -> f32 {
+        if self.has(StatusKind::Stun) {
+            return -1.;
+        }
+
+        self.0
+            .iter()
+            .map(|effect| match effect.kind {
+                StatusKind::Slow => -effect.strength,
+                StatusKind::Haste => effect.strength,
+                _ => 0.,
+            })
+            .sum::<f32>()
+            .max(-1.)
+    }
+
+    fn damage_reduction(&self) -> f32 {
+        // Every shield blocks its part of what the others let through
+        1. - self
+            .0
+            .iter()
+            .filter(|effect| effect.kind == StatusKind::Shield)
+            .map(|effect| 1. - effect.strength.clamp(0., 1.))
+            .product::<f32>()
+    }
+}
+
+fn time_left(timer: &Timer) -> f32 {
+    timer.duration().as_secs_f32() - timer.elapsed_secs()
+}
+
+/// Gives the player the effect when picked up
+#[derive(Component)]
+pub struct StatusPickup {
+    pub effect: StatusEffectDefinition,
+    lifetime: Timer,
+}
+
+/// The color of the sprite from before an effect tinted it, put back once the effects are gone
+#[derive(Component)]
+struct BaseColor(Color);
+
+fn apply_weapon_effects(
+    mut entity_hit_events: EventReader<EntityHitEvent>,
+    weapons: Query<&Weapon>,
+    weak_points: Query<&Parent, With<WeakPoint>>,
+    mut targets: Query<&mut StatusEffects>,
+    network_role: Res<NetworkRole>,
+) {
+    if !network_role.is_authority() {
+        return;
+    }
+
+    // Burns and explosions keep the weapon as their source, only the beam itself puts the effect on
+    for event in entity_hit_events
+        .iter()
+        .filter(|event| event.source.kind.from_beam())
+    {
+        let effect = match weapons.get(event.source.entity) {
+            Ok(weapon) => match &weapon.definition.effect {
+                Some(effect) => effect,
+                None => continue,
+            },
+            Err(_) => continue,
+        };
+
+        // Weak points pass the effect on to the enemy they belong to
+        let target = weak_points
+            .get(event.entity)
+            .map(|parent| parent.get())
+            .unwrap_or(event.entity);
+
+        if let Ok(mut status_effects) = targets.get_mut(target) {
+            status_effects.apply(effect, Some(event.source));
+        }
+    }
+}
+
+fn tick_status_effects(
+    mut targets: Query<(
+        &mut StatusEffects,
+        Option<&mut Movement>,
+        Option<&mut Health>,
+    )>,
+    time: Res<Time>,
+) {
+    for (mut status_effects, movement, health) in targets.iter_mut() {
+        for effect in status_effects.0.iter_mut() {
+            effect.timer.tick(time.delta());
+        }
+
+        status_effects.0.retain(|effect| !effect.timer.finished());
+
+        if let Some(mut movement) = movement {
+            movement.speed_modifier = status_effects.speed_modifier();
+        }
+
+        if let Some(mut health) = health {
+            health.set_damage_reduction(status_effects.damage_reduction());
+        }
+    }
+}
+
+fn burn(
+    enemies: Query<(Entity, &Transform, &StatusEffects), With<Enemy>>,
+    mut players: Query<(&StatusEffects, &mut Health), With<Player>>,
+    mut entity_hit_events: EventWriter<EntityHitEvent>,
+    mut player_damaged_events: EventWriter<PlayerDamagedEvent>,
+    time: Res<Time>,
+    network_role: Res<NetworkRole>,
+) {
+    if !network_role.is_authority() {
+        return;
+    }
+
+    let burns = |status_effects: &StatusEffects| {
+        status_effects
+            .0
+            .iter()
+            .filter(|effect| effect.kind == StatusKind::Burn)
+            .map(|effect| (effect.strength * time.delta_seconds(), effect.source))
+            .collect::<Vec<_>>()
+    };
+
+    // Enemies burn through the same damage pipeline as the lasers so the kills count
+    for (entity, transform, status_effects) in enemies.iter() {
+        for (amount, source) in burns(status_effects) {
+            entity_hit_events.send(EntityHitEvent {
+                entity,
+                amount,
+                source: DamageSource {
+                    kind: DamageKind::Burn,
+                    entity: source.map(|source| source.entity).unwrap_or(entity),
+                },
+                point: transform.translation.xy(),
+            });
+        }
+    }
+
+    for (status_effects, mut health) in players.iter_mut() {
+        for (amount, _) in burns(status_effects) {
+            health.take_damage(amount);
+            player_damaged_events.send(PlayerDamagedEvent { amount });
+        }
+    }
+}
+
+fn tint_status_effects(
+    mut commands: Commands,
+    mut targets: Query<(Entity, &StatusEffects, &mut Sprite, Option<&BaseColor>)>,
+) {
+    for (entity, status_effects, mut sprite, base_color) in targets.iter_mut() {
+        // The latest effect shows
+        match (status_effects.0.last(), base_color) {
+            (Some(effect), Some(_)) => {
+                sprite.color = effect.kind.color();
+            }
+            (Some(effect), None) => {
+                commands.entity(entity).insert(BaseColor(sprite.color));
+                sprite.color = effect.kind.color();
+            }
+            (None, Some(base_color)) => {
+                sprite.color = base_color.0;
+                commands.entity(entity).remove::<BaseColor>();
+            }
+            // Left alone so other tints aren't overwritten
+            (None, None) => {}
+        }
+    }
+}
+
+fn drop_pickups(
+    mut commands: Commands,
+    mut enemy_damaged_events: EventReader<EnemyDamagedEvent>,
+    network_role: Res<NetworkRole>,
+) {
+    if !network_role.is_authority() {
+        return;
+    }
+
+    let mut rng = rand::thread_rng();
+
+    for event in enemy_damaged_events.iter() {
+        if !event.killed || !rng.gen_bool(PICKUP_DROP_CHANCE) {
+            continue;
+        }
+
+        let effect = if rng.gen_bool(0.5) {
+            StatusEffectDefinition {
+                kind: StatusKind::Shield,
+                strength: 0.5,
+                duration: 8.,
+            }
+        } else {
+            StatusEffectDefinition {
+                kind: StatusKind::Haste,
+                strength: 0.5,
+                duration: 6.,
+            }
+        };
+
+        spawn_pickup(&mut commands, event.position, effect);
+    }
+}
+
+pub fn spawn_pickup(
+    commands: &mut Commands,
+    position: Vec2,
+    effect: StatusEffectDefinition,
+) -> Entity {
+    commands
+        .spawn_bundle(SpriteBundle {
+            sprite: Sprite {
+                color: effect.kind.color(),
+                custom_size: Some(Vec2::splat(PICKUP_SIZE)),
+                ..default()
+            },
+            transform: Transform::from_translation(position.extend(1.5)),
+            ..default()
+        })
+        .insert(StatusPickup {
+            effect,
+            lifetime: Timer::from_seconds(PICKUP_LIFETIME, false),
+        })
+        .insert(Name::new("Pickup"))
+        .id()
+}
+
+fn collect_pickups(
+    mut commands: Commands,
+    mut pickups: Query<(Entity, &Transform, &mut StatusPickup)>,
+    mut players: Query<(&Transform, &mut StatusEffects), With<Player>>,
+    time: Res<Time>,
+    network_role: Res<NetworkRole>,
+) {
+    // Clients see the pickups the host still has, see [crate::network]
+    if !network_role.is_authority() {
+        return;
+    }
+
+    for (entity, transform, mut pickup) in pickups.iter_mut() {
+        if pickup.lifetime.tick(time.delta()).finished() {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+
+        let position = transform.translation.xy();
+
+        let player = players.iter_mut().find(|(player_transform, _)| {
+            player_transform.translation.xy().distance(position) < PICKUP_SIZE + 16.
+        });
+
+        if let Some((_, mut status_effects)) = player {
+            status_effects.apply(&pickup.effect, None);
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+fn clean_pickups(mut commands: Commands, pickups: Query<Entity, With<StatusPickup>>) {
+    for pickup in pickups.iter() {
+        commands.entity(pickup).despawn_recursive();
+    }
+}
@@ -0,0 +1,69 @@
+use bevy::prelude::*;
+use serde::{de::DeserializeOwned, Serialize};
+
+/// Loads data saved with [save] in an earlier session
+pub fn load<T: DeserializeOwned>(key: &str) -> Option<T> {
+    let data = read(key)?;
+
+    match ron::from_str(&data) {
+        Ok(value) => Some(value),
+        Err(error) => {
+            warn!("Failed to parse the saved {}: {}", key, error);
+            None
+        }
+    }
+}
+
+/// Saves data on the player's machine so that it survives restarting the game
+pub fn save<T: Serialize>(key: &str, value: &T) {
+    match ron::to_string(value) {
+        Ok(data) => write(key, &data),
+        Err(error) => warn!("Failed to serialize the {}: {}", key, error),
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+const SAVE_DIRECTORY: &str = "saves";
+
+#[cfg(not(target_arch = "wasm32"))]
+fn read(key: &str) -> Option<String> {
+    std::fs::read_to_string(std::path::Path::new(SAVE_DIRECTORY).join(format!("{}.ron", key))).ok()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn write(key: &str, data: &str) {
+    let directory = std::path::Path::new(SAVE_DIRECTORY);
+
+    if let Err(error) = std::fs::create_dir_all(directory)
+        .and_then(|_| std::fs::write(directory.join(format!("{}.ron", key)), data))
+    {
+        warn!("Failed to save the {}: {}", key, error);
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn local_storage() -> Option<web_sys::Storage> {
+    web_sys::window()?.local_storage().ok()?
+}
+
+#[cfg(target_arch = "wasm32")]
+fn read(key: &str) -> Option<String> {
+    local_storage()?
+        .get_item(&format!("laser_beam.{}", key))
+        .ok()?
+}
+
+#[cfg(target_arch = "wasm32")]
+fn write(key: &str, data: &str) {
+    let saved = local_storage()
+        .map(|storage| {
+            storage
+                .set_item(&format!("laser_beam.{}", key), data)
+                .is_ok()
+        })
+        .unwrap_or(false);
+
+    if !saved {
+        warn!("Failed to save the {}", key);
+    }
+}
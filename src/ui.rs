@@ -1,6 +1,22 @@
 use bevy::prelude::*;
 
-use crate::{character::Health, loading::FontAssets, player::Player, score::Score, GameState};
+use bevy::math::Vec3Swizzles;
+
+use crate::{
+    actions::Actions,
+    boss::Boss,
+    camera::screen_size,
+    character::Health,
+    enemy::Enemy,
+    game_area::EnemySpawn,
+    indicators::ArrowTexture,
+    loading::FontAssets,
+    player::{Dash, Player, PlayerCount, PlayerId},
+    score::{Score, ScoreMultiplier},
+    stats::RunStats,
+    weapon::{Weapon, WeaponStatus},
+    GameState, GAME_AREA_HEIGHT, GAME_AREA_WIDTH, WALL_HEIGHT, WALL_WIDTH,
+};
 
 pub struct HealthBarPlugin;
 
@@ -10,22 +26,102 @@ impl Plugin for HealthBarPlugin {
             .add_system_set(
                 SystemSet::on_update(GameState::Playing)
                     .with_system(health_bar_update)
-                    .with_system(score_update),
+                    .with_system(beam_gauge_update)
+                    .with_system(dash_gauge_update)
+                    .with_system(weapon_name_update)
+                    .with_system(score_update)
+                    .with_system(run_time_update)
+                    .with_system(kills_update)
+                    .with_system(score_multiplier_update)
+                    .with_system(boss_health_bar_update)
+                    .with_system(toggle_minimap)
+                    .with_system(spawn_minimap_icons)
+                    .with_system(update_minimap_icons),
             )
             .add_system_set(SystemSet::on_exit(GameState::Playing).with_system(clean_ui));
     }
 }
 
 #[derive(Component)]
-pub struct HealthBar;
+pub struct HealthBar(pub PlayerId);
+
+#[derive(Component)]
+pub struct BeamGauge(pub PlayerId);
+
+#[derive(Component)]
+pub struct WeaponNameUi(pub PlayerId);
+
+#[derive(Component)]
+pub struct DashGauge(pub PlayerId);
 
 #[derive(Component)]
 pub struct ScoreUi;
 
+#[derive(Component)]
+pub struct RunTimeUi;
+
+#[derive(Component)]
+pub struct KillsUi;
+
+#[derive(Component)]
+pub struct ScoreMultiplierUi;
+
+#[derive(Component)]
+pub struct BossHealthBar;
+
+/// Holds the [BossHealthBar], only shown while a boss is around
+#[derive(Component)]
+struct BossHealthBarFrame;
+
 #[derive(Component)]
 pub struct Ui;
 
-fn overlay(mut commands: Commands, fonts: Res<FontAssets>) {
+/// The part of the minimap showing the inside of the game area
+#[derive(Component)]
+pub struct Minimap {
+    /// Size of one world unit on the minimap
+    scale: f32,
+}
+
+/// The minimap including the walls around it, hidden and shown with [Actions::toggle_minimap]
+#[derive(Component)]
+struct MinimapFrame;
+
+/// Shows the given entity on the minimap
+#[derive(Component)]
+struct MinimapIcon(Entity);
+
+/// The window height at which the HUD is drawn in full size
+pub const HUD_REFERENCE_HEIGHT: f32 = 720.;
+
+pub const BEAM_CHARGING_COLOR: Color = Color::rgb(0.3, 0.5, 1.);
+pub const BEAM_BEAMING_COLOR: Color = Color::rgb(1., 0.35, 0.3);
+pub const DASH_GAUGE_COLOR: Color = Color::rgb(0.85, 0.85, 0.85);
+
+pub const MINIMAP_MAX_WIDTH: f32 = 200.;
+pub const MINIMAP_SCREEN_FRACTION: f32 = 0.2;
+
+fn overlay(
+    mut commands: Commands,
+    fonts: Res<FontAssets>,
+    windows: Res<Windows>,
+    player_count: Res<PlayerCount>,
+) {
+    let window_size = screen_size(&windows);
+
+    // Scale the minimap down on small screens (like the web build)
+    let minimap_scale =
+        (window_size.x * MINIMAP_SCREEN_FRACTION).min(MINIMAP_MAX_WIDTH) / GAME_AREA_WIDTH;
+
+    // Same goes for the text and the bars
+    let hud_scale = (window_size.y / HUD_REFERENCE_HEIGHT).clamp(0.6, 1.);
+
+    let text_style = |font_size: f32| TextStyle {
+        font: fonts.fira_sans.clone(),
+        font_size,
+        color: Color::WHITE,
+    };
+
     commands
         .spawn_bundle(NodeBundle {
             style: Style {
@@ -39,67 +135,486 @@ fn overlay(mut commands: Commands, fonts: Res<FontAssets>) {
             ..default()
         })
         .with_children(|parent| {
-            // Health bar
+            // Run timer
+            parent
+                .spawn_bundle(
+                    TextBundle::from_section("0:00", text_style(hud_scale * 27.)).with_style(
+                        Style {
+                            position_type: PositionType::Absolute,
+                            position: UiRect {
+                                top: Val::Px(20.),
+                                left: Val::Px(20.),
+                                ..default()
+                            },
+                            ..default()
+                        },
+                    ),
+                )
+                .insert(RunTimeUi);
+
+            // Boss health, at the top in the middle
+            parent
+                .spawn_bundle(NodeBundle {
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        position: UiRect {
+                            top: Val::Px(20.),
+                            left: Val::Percent(30.),
+                            ..default()
+                        },
+                        size: Size::new(Val::Percent(40.), Val::Undefined),
+                        flex_direction: FlexDirection::ColumnReverse,
+                        align_items: AlignItems::Center,
+                        display: Display::None,
+                        ..default()
+                    },
+                    color: Color::NONE.into(),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent.spawn_bundle(TextBundle::from_section(
+                        "Boss",
+                        text_style(hud_scale * 22.),
+                    ));
+
+                    spawn_bar(parent, 20. * hud_scale, Color::PURPLE, BossHealthBar);
+                })
+                .insert(BossHealthBarFrame);
+
+            // Bars, from the bottom up
+            parent
+                .spawn_bundle(NodeBundle {
+                    style: Style {
+                        size: Size::new(Val::Percent(35.), Val::Undefined),
+                        flex_direction: FlexDirection::Column,
+                        ..default()
+                    },
+                    color: Color::NONE.into(),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    for id in (0..player_count.0).map(PlayerId) {
+                        parent
+                            .spawn_bundle(NodeBundle {
+                                style: Style {
+                                    size: Size::new(Val::Percent(100.), Val::Undefined),
+                                    align_items: AlignItems::Center,
+                                    ..default()
+                                },
+                                color: Color::NONE.into(),
+                                ..default()
+                            })
+                            .with_children(|parent| {
+                                // Tell the bars apart in co-op
+                                if player_count.0 > 1 {
+                                    parent.spawn_bundle(
+                                        TextBundle::from_section(
+                                            format!("P{}", id.0 + 1),
+                                            TextStyle {
+                                                color: id.color(),
+                                                ..text_style(hud_scale * 18.)
+                                            },
+                                        )
+                                        .with_style(
+                                            Style {
+                                                margin: UiRect {
+                                                    right: Val::Px(8.),
+                                                    ..default()
+                                                },
+                                                ..default()
+                                            },
+                                        ),
+                                    );
+                                }
+
+                                parent
+                                    .spawn_bundle(NodeBundle {
+                                        style: Style {
+                                            flex_direction: FlexDirection::Column,
+                                            flex_grow: 1.,
+                                            ..default()
+                                        },
+                                        color: Color::NONE.into(),
+                                        ..default()
+                                    })
+                                    .with_children(|parent| {
+                                        // Health bar
+                                        spawn_bar(
+                                            parent,
+                                            25. * hud_scale,
+                                            Color::RED,
+                                            HealthBar(id),
+                                        );
+
+                                        // Beam charge and cooldown
+                                        spawn_bar(
+                                            parent,
+                                            12. * hud_scale,
+                                            BEAM_CHARGING_COLOR,
+                                            BeamGauge(id),
+                                        );
+
+                                        // Dash cooldown
+                                        spawn_bar(
+                                            parent,
+                                            8. * hud_scale,
+                                            DASH_GAUGE_COLOR,
+                                            DashGauge(id),
+                                        );
+
+                                        parent
+                                            .spawn_bundle(TextBundle::from_section(
+                                                "",
+                                                text_style(hud_scale * 16.),
+                                            ))
+                                            .insert(WeaponNameUi(id));
+                                    });
+                            });
+                    }
+                });
+
+            // Numbers, from the bottom up
             parent
                 .spawn_bundle(NodeBundle {
                     style: Style {
-                        size: Size::new(Val::Percent(35.), Val::Px(25.)),
-                        padding: UiRect::all(Val::Px(5.)),
+                        flex_direction: FlexDirection::Column,
+                        align_items: AlignItems::FlexEnd,
                         ..default()
                     },
-                    color: Color::BLACK.into(),
+                    color: Color::NONE.into(),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent
+                        .spawn_bundle(TextBundle::from_section("0", text_style(hud_scale * 27.)))
+                        .insert(ScoreUi);
+
+                    parent
+                        .spawn_bundle(TextBundle::from_section(
+                            "Kills: 0",
+                            text_style(hud_scale * 18.),
+                        ))
+                        .insert(KillsUi);
+
+                    parent
+                        .spawn_bundle(TextBundle::from_section(
+                            "",
+                            TextStyle {
+                                color: Color::GOLD,
+                                ..text_style(hud_scale * 22.)
+                            },
+                        ))
+                        .insert(ScoreMultiplierUi);
+                });
+
+            // Minimap, the frame stands for the walls
+            parent
+                .spawn_bundle(NodeBundle {
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        position: UiRect {
+                            top: Val::Px(20.),
+                            right: Val::Px(20.),
+                            ..default()
+                        },
+                        padding: UiRect {
+                            left: Val::Px(WALL_WIDTH * minimap_scale),
+                            right: Val::Px(WALL_WIDTH * minimap_scale),
+                            top: Val::Px(WALL_HEIGHT * minimap_scale),
+                            bottom: Val::Px(WALL_HEIGHT * minimap_scale),
+                        },
+                        ..default()
+                    },
+                    color: Color::rgba(0.5, 0.5, 0.5, 0.8).into(),
                     ..default()
                 })
                 .with_children(|parent| {
                     parent
                         .spawn_bundle(NodeBundle {
                             style: Style {
-                                size: Size::new(Val::Percent(100.), Val::Percent(100.)),
+                                size: Size::new(
+                                    Val::Px((GAME_AREA_WIDTH - WALL_WIDTH * 2.) * minimap_scale),
+                                    Val::Px((GAME_AREA_HEIGHT - WALL_HEIGHT * 2.) * minimap_scale),
+                                ),
                                 ..default()
                             },
-                            color: Color::RED.into(),
+                            color: Color::rgba(0.05, 0.05, 0.05, 0.8).into(),
                             ..default()
                         })
-                        .insert(HealthBar);
-                });
+                        .insert(Minimap {
+                            scale: minimap_scale,
+                        });
+                })
+                .insert(Name::new("Minimap"))
+                .insert(MinimapFrame);
+        })
+        .insert(Ui);
+}
 
-            parent
-                .spawn_bundle(TextBundle::from_section(
-                    "0",
-                    TextStyle {
-                        font: fonts.fira_sans.clone(),
-                        font_size: 27.,
-                        color: Color::WHITE,
+fn toggle_minimap(actions: Res<Actions>, mut minimap: Query<&mut Style, With<MinimapFrame>>) {
+    if !actions.toggle_minimap {
+        return;
+    }
+
+    for mut style in minimap.iter_mut() {
+        style.display = match style.display {
+            Display::None => Display::Flex,
+            Display::Flex => Display::None,
+        };
+    }
+}
+
+fn spawn_minimap_icons(
+    mut commands: Commands,
+    minimap: Query<Entity, With<Minimap>>,
+    players: Query<(Entity, &PlayerId), Added<Player>>,
+    enemies: Query<Entity, Added<Enemy>>,
+    enemy_spawns: Query<Entity, Added<EnemySpawn>>,
+    arrow_texture: Res<ArrowTexture>,
+) {
+    let minimap = match minimap.get_single() {
+        Ok(minimap) => minimap,
+        Err(_) => return,
+    };
+
+    let mut spawn_icon =
+        |entity: Entity, size: f32, color: Color, image: Option<&Handle<Image>>| {
+            let icon = commands
+                .spawn_bundle(ImageBundle {
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        size: Size::new(Val::Px(size), Val::Px(size)),
+                        ..default()
                     },
-                ))
-                .insert(ScoreUi);
+                    image: image
+                        .map(|image| UiImage(image.clone()))
+                        .unwrap_or_default(),
+                    color: color.into(),
+                    ..default()
+                })
+                .insert(MinimapIcon(entity))
+                .id();
+
+            commands.entity(minimap).add_child(icon);
+        };
+
+    for enemy_spawn in enemy_spawns.iter() {
+        spawn_icon(enemy_spawn, 6., Color::rgb(0.55, 0.65, 1.), None);
+    }
+
+    for enemy in enemies.iter() {
+        spawn_icon(enemy, 4., Color::RED, None);
+    }
+
+    for (player, id) in players.iter() {
+        spawn_icon(player, 10., id.color(), Some(&arrow_texture.0));
+    }
+}
+
+fn update_minimap_icons(
+    mut commands: Commands,
+    minimap: Query<&Minimap>,
+    mut icons: Query<(Entity, &MinimapIcon, &mut Style, &mut Transform)>,
+    transforms: Query<&Transform, Without<MinimapIcon>>,
+) {
+    let minimap = match minimap.get_single() {
+        Ok(minimap) => minimap,
+        Err(_) => return,
+    };
+
+    for (entity, icon, mut style, mut transform) in icons.iter_mut() {
+        let target = match transforms.get(icon.0) {
+            Ok(target) => target,
+            Err(_) => {
+                commands.entity(entity).despawn_recursive();
+                continue;
+            }
+        };
+
+        let size = match style.size.width {
+            Val::Px(size) => size,
+            _ => 0.,
+        };
+
+        // Relative to the inside of the walls
+        let position = (target.translation.xy()
+            + Vec2::new(
+                GAME_AREA_WIDTH / 2. - WALL_WIDTH,
+                GAME_AREA_HEIGHT / 2. - WALL_HEIGHT,
+            ))
+            * minimap.scale
+            - Vec2::splat(size / 2.);
+
+        style.position = UiRect {
+            left: Val::Px(position.x),
+            bottom: Val::Px(position.y),
+            ..default()
+        };
+
+        transform.rotation = target.rotation;
+    }
+}
+
+fn spawn_bar(parent: &mut ChildBuilder, height: f32, color: Color, marker: impl Component) {
+    parent
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.), Val::Px(height)),
+                padding: UiRect::all(Val::Px(height / 5.)),
+                margin: UiRect {
+                    top: Val::Px(5.),
+                    ..default()
+                },
+                ..default()
+            },
+            color: Color::BLACK.into(),
+            ..default()
         })
-        .insert(Ui);
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(NodeBundle {
+                    style: Style {
+                        size: Size::new(Val::Percent(100.), Val::Percent(100.)),
+                        ..default()
+                    },
+                    color: color.into(),
+                    ..default()
+                })
+                .insert(marker);
+        });
 }
 
 fn health_bar_update(
-    player_query: Query<&Health, With<Player>>,
-    mut health_bar_query: Query<&mut Style, With<HealthBar>>,
+    player_query: Query<(&PlayerId, &Health), With<Player>>,
+    mut health_bar_query: Query<(&HealthBar, &mut Style)>,
     time: Res<Time>,
 ) {
-    let player_health = player_query.single();
-    let mut health_bar_style = health_bar_query.single_mut();
-
     let rate = 6. * time.delta_seconds();
 
-    let target = (player_health.get_health() / player_health.get_max_health()) * 100.;
-    let mut current = match health_bar_style.size.width {
-        Val::Percent(val) => val,
-        _ => panic!("health bar width not in percent"),
-    };
-    current += (target - current) * rate;
-    health_bar_style.size.width = Val::Percent(current);
+    for (health_bar, mut health_bar_style) in health_bar_query.iter_mut() {
+        // Fallen players are despawned in co-op
+        let target = player_query
+            .iter()
+            .find(|(id, _)| **id == health_bar.0)
+            .map(|(_, health)| (health.get_health() / health.get_max_health()) * 100.)
+            .unwrap_or(0.);
+
+        let mut current = match health_bar_style.size.width {
+            Val::Percent(val) => val,
+            _ => panic!("health bar width not in percent"),
+        };
+        current += (target - current) * rate;
+        health_bar_style.size.width = Val::Percent(current);
+    }
+}
+
+fn beam_gauge_update(
+    weapons: Query<(&Weapon, &Parent)>,
+    players: Query<&PlayerId>,
+    mut beam_gauge_query: Query<(&BeamGauge, &mut Style, &mut UiColor)>,
+) {
+    for (beam_gauge, mut style, mut color) in beam_gauge_query.iter_mut() {
+        let weapon = weapons
+            .iter()
+            .find(|(_, parent)| players.get(parent.get()).ok() == Some(&beam_gauge.0))
+            .map(|(weapon, _)| weapon);
+
+        // Fills up while cooling down and drains while beaming
+        let (percent, gauge_color) = match weapon {
+            Some(weapon) => match weapon.status {
+                WeaponStatus::Idle => (1. - weapon.cooldown.percent_left(), BEAM_CHARGING_COLOR),
+                WeaponStatus::Beaming => (weapon.beaming_time.percent_left(), BEAM_BEAMING_COLOR),
+            },
+            None => (0., BEAM_CHARGING_COLOR),
+        };
+
+        style.size.width = Val::Percent(percent * 100.);
+        *color = gauge_color.into();
+    }
+}
+
+fn dash_gauge_update(
+    players: Query<(&PlayerId, &Dash)>,
+    mut dash_gauge_query: Query<(&DashGauge, &mut Style)>,
+) {
+    for (dash_gauge, mut style) in dash_gauge_query.iter_mut() {
+        // Full when the player can dash again
+        let percent = players
+            .iter()
+            .find(|(id, _)| **id == dash_gauge.0)
+            .map(|(_, dash)| dash.cooldown.percent())
+            .unwrap_or(0.);
+
+        style.size.width = Val::Percent(percent * 100.);
+    }
+}
+
+fn weapon_name_update(
+    weapons: Query<(&Weapon, &Parent)>,
+    players: Query<&PlayerId>,
+    mut weapon_name_query: Query<(&WeaponNameUi, &mut Text)>,
+) {
+    for (weapon_name, mut text) in weapon_name_query.iter_mut() {
+        let name = weapons
+            .iter()
+            .find(|(_, parent)| players.get(parent.get()).ok() == Some(&weapon_name.0))
+            .map(|(weapon, _)| weapon.definition.name.as_str())
+            .unwrap_or_default();
+
+        if text.sections[0].value != name {
+            text.sections[0].value = name.to_string();
+        }
+    }
 }
 
 fn score_update(mut score_ui: Query<&mut Text, With<ScoreUi>>, score: Res<Score>) {
     score_ui.single_mut().sections[0].value = format!("{}", score.0);
 }
 
+fn run_time_update(mut run_time_ui: Query<&mut Text, With<RunTimeUi>>, stats: Res<RunStats>) {
+    let seconds = stats.time_survived.as_secs();
+
+    run_time_ui.single_mut().sections[0].value = format!("{}:{:02}", seconds / 60, seconds % 60);
+}
+
+fn kills_update(mut kills_ui: Query<&mut Text, With<KillsUi>>, stats: Res<RunStats>) {
+    kills_ui.single_mut().sections[0].value = format!("Kills: {}", stats.enemies_killed);
+}
+
+fn score_multiplier_update(
+    mut score_multiplier_ui: Query<&mut Text, With<ScoreMultiplierUi>>,
+    score_multiplier: Res<ScoreMultiplier>,
+) {
+    // No need to show the default multiplier
+    score_multiplier_ui.single_mut().sections[0].value = if score_multiplier.0 > 1 {
+        format!("x{}", score_multiplier.0)
+    } else {
+        String::new()
+    };
+}
+
+fn boss_health_bar_update(
+    bosses: Query<&Health, With<Boss>>,
+    mut frame: Query<&mut Style, (With<BossHealthBarFrame>, Without<BossHealthBar>)>,
+    mut health_bar: Query<&mut Style, (With<BossHealthBar>, Without<BossHealthBarFrame>)>,
+) {
+    let boss_health = bosses.iter().next();
+
+    for mut style in frame.iter_mut() {
+        style.display = if boss_health.is_some() {
+            Display::Flex
+        } else {
+            Display::None
+        };
+    }
+
+    if let Some(health) = boss_health {
+        for mut style in health_bar.iter_mut() {
+            style.size.width = Val::Percent(health.get_health() / health.get_max_health() * 100.);
+        }
+    }
+}
+
 fn clean_ui(mut commands: Commands, ui_query: Query<Entity, With<Ui>>) {
     commands.entity(ui_query.single()).despawn_recursive();
 }
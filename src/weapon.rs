@@ -1,29 +1,57 @@
 use std::f32::consts::PI;
 
 use crate::{
+    actions::{PlayerActions, WeaponSwitch},
+    character::{DamageKind, DamageSource},
+    lighting::LineLight2d,
     loading::{AudioAssets, TextureAssets},
+    mirror::Mirror,
     player::Player,
+    status::StatusEffectDefinition,
     GameState,
 };
-use bevy::prelude::*;
+use bevy::{prelude::*, render::texture::DEFAULT_IMAGE_HANDLE};
 use bevy_kira_audio::{Audio, AudioControl, AudioInstance, AudioTween};
 use bevy_rapier2d::prelude::*;
+use serde::Deserialize;
 
 pub struct WeaponPlugin;
 
 impl Plugin for WeaponPlugin {
     fn build(&self, app: &mut App) {
         app.add_event::<EntityHitEvent>()
-            .add_system_set(SystemSet::on_enter(GameState::Playing).with_system(setup_laser))
+            .insert_resource(load_weapon_definitions())
+            .add_system_set(SystemSet::on_enter(GameState::Playing).with_system(setup_laser_sound))
             .add_system_set(SystemSet::on_exit(GameState::Playing).with_system(drop_laser))
-            .add_system_set(SystemSet::on_update(GameState::Playing).with_system(shoot).with_system(play_laser_sound));
+            .add_system_set(
+                SystemSet::on_update(GameState::Playing)
+                    .with_system(spawn_lasers)
+                    .with_system(switch_weapons.after("player_actions"))
+                    .with_system(shoot)
+                    .with_system(drop_orphaned_lasers)
+                    .with_system(play_laser_sound),
+            );
     }
 }
 
 pub const LASER_END_WIDTH: f32 = 50.;
 pub const LASER_END_HEIGHT: f32 = 50.;
 
-pub struct EntityHitEvent(pub Entity);
+/// Part of the damage dealt to entities only touched by the edge of the beam
+pub const BEAM_GRAZE_MULTIPLIER: f32 = 0.5;
+
+/// Beams stop after bouncing off this many mirrors
+pub const MAX_MIRROR_BOUNCES: usize = 3;
+
+/// Sent every frame an entity stays in a laser
+pub struct EntityHitEvent {
+    pub entity: Entity,
+    /// Damage dealt this frame
+    pub amount: f32,
+    pub source: DamageSource,
+    /// Where the laser touched the entity
+    pub point: Vec2,
+}
 
 #[derive(Bundle)]
 pub struct WeaponBundle {
@@ -36,25 +64,117 @@ pub enum WeaponStatus {
     Beaming,
 }
 
+#[derive(Deserialize, Clone, Copy, PartialEq, Debug)]
+pub enum WeaponKind {
+    /// One continuous beam straight ahead
+    Beam,
+    /// A short shot going through everything in its way
+    Rail,
+    /// Several rays fanned out over `angle` degrees
+    Spread { rays: usize, angle: f32 },
+    /// A beam turning from one side to the other over `angle` degrees while firing
+    Sweep { angle: f32 },
+}
+
+impl WeaponKind {
+    pub fn ray_count(&self) -> usize {
+        match *self {
+            WeaponKind::Spread { rays, .. } => rays.max(1),
+            _ => 1,
+        }
+    }
+
+    /// Angles of the rays in radians relative to where the player is facing,
+    /// `progress` goes from 0 to 1 while firing
+    fn ray_angles(&self, progress: f32) -> Vec<f32> {
+        match *self {
+            WeaponKind::Beam | WeaponKind::Rail => vec![0.],
+            WeaponKind::Spread { angle, .. } => {
+                let rays = self.ray_count();
+
+                if rays == 1 {
+                    return vec![0.];
+                }
+
+                (0..rays)
+                    .map(|ray| (ray as f32 / (rays - 1) as f32 - 0.5) * angle.to_radians())
+                    .collect()
+            }
+            WeaponKind::Sweep { angle } => vec![(0.5 - progress) * angle.to_radians()],
+        }
+    }
+
+    pub fn pierces(&self) -> bool {
+        *self == WeaponKind::Rail
+    }
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct WeaponDefinition {
+    pub name: String,
+    pub kind: WeaponKind,
+    /// Seconds it takes to charge the weapon
+    pub cooldown: f32,
+    /// Seconds the weapon fires for once charged
+    pub firing_time: f32,
+    pub range: f32,
+    /// How wide each ray is, both for hitting things and for drawing it
+    pub beam_width: f32,
+    /// Damage each ray deals per second to whatever is in it
+    pub damage_per_second: f32,
+    pub damage_kind: DamageKind,
+    /// Status effect put on whatever is in the laser
+    #[serde(default)]
+    pub effect: Option<StatusEffectDefinition>,
+}
+
+/// The weapons defined in `assets/weapons.ron`, one per slot
+#[derive(Deref)]
+pub struct WeaponDefinitions(pub Vec<WeaponDefinition>);
+
 #[derive(Component)]
 pub struct Weapon {
+    pub definition: WeaponDefinition,
+    /// Index of the definition in [WeaponDefinitions]
+    pub slot: usize,
     pub cooldown: Timer,
     pub beaming_time: Timer,
     pub status: WeaponStatus,
+    /// Whether the rays go through everything, raised by perks
+    pub pierces: bool,
 }
 
-impl Default for Weapon {
-    fn default() -> Self {
+impl Weapon {
+    pub fn new(slot: usize, definitions: &WeaponDefinitions) -> Self {
+        let definition = definitions[slot].clone();
+
         Self {
-            cooldown: Timer::from_seconds(10., false),
-            beaming_time: Timer::from_seconds(2., false),
+            cooldown: Timer::from_seconds(definition.cooldown, false),
+            beaming_time: Timer::from_seconds(definition.firing_time, false),
             status: WeaponStatus::Idle,
+            pierces: definition.kind.pierces(),
+            definition,
+            slot,
         }
     }
 }
 
+/// One ray of the given weapon, kept apart from the weapon so it doesn't inherit the scale of the player
 #[derive(Component)]
-struct Laser;
+struct Laser {
+    weapon: Entity,
+    ray: usize,
+    /// Which part of the ray it shows, the ray goes on in a new segment after bouncing off a [Mirror]
+    segment: usize,
+}
+
+/// Color of the lasers of the player, picked on the upgrade screen
+#[derive(Component, Clone, Copy)]
+pub struct LaserColor(pub Color);
+
+/// Names of the weapons the player can't switch to, because they weren't unlocked on the upgrade screen
+#[derive(Component, Default)]
+pub struct LockedWeapons(pub Vec<String>);
 
 #[derive(Bundle)]
 struct LaserBundle {
@@ -77,35 +197,75 @@ struct LaserEndBundle {
 #[derive(Deref, DerefMut)]
 struct LaserSound(Handle<AudioInstance>);
 
-fn setup_laser(
+fn load_weapon_definitions() -> WeaponDefinitions {
+    let definitions: Vec<WeaponDefinition> = ron::from_str(include_str!("../assets/weapons.ron"))
+        .expect("Failed to parse the weapon definitions");
+
+    assert!(!definitions.is_empty(), "There are no weapons defined");
+
+    WeaponDefinitions(definitions)
+}
+
+fn spawn_lasers(
     mut commands: Commands,
+    weapons: Query<(Entity, &Weapon, &Parent), Added<Weapon>>,
+    laser_colors: Query<&LaserColor>,
     textures: Res<TextureAssets>,
-    audio: Res<Audio>,
-    sounds: Res<AudioAssets>,
 ) {
-    commands
-        .spawn_bundle(LaserBundle {
-            sprite_bundle: SpriteBundle {
-                texture: textures.laser_texture.clone(),
-                visibility: Visibility { is_visible: false },
-                ..default()
-            },
-            laser: Laser,
-        })
-        .insert(Name::new("Laser"));
-
-    commands
-        .spawn_bundle(LaserEndBundle {
-            sprite_bundle: SpriteBundle {
-                texture: textures.laser_end_texture.clone(),
-                visibility: Visibility { is_visible: false },
-                ..default()
-            },
-            laser: Laser,
-            laser_end: LaserEnd,
-        })
-        .insert(Name::new("LaserEnd"));
+    for (entity, weapon, parent) in weapons.iter() {
+        let laser_color = laser_colors.get(parent.get()).ok();
 
+        spawn_weapon_lasers(&mut commands, &textures, entity, weapon, laser_color);
+    }
+}
+
+fn spawn_weapon_lasers(
+    commands: &mut Commands,
+    textures: &TextureAssets,
+    entity: Entity,
+    weapon: &Weapon,
+    laser_color: Option<&LaserColor>,
+) {
+    // The laser texture is red through and through, so other colors are drawn on a plain white one
+    let (texture, color, light_color) = match laser_color {
+        Some(LaserColor(color)) => (DEFAULT_IMAGE_HANDLE.typed(), *color, *color),
+        None => (textures.laser_texture.clone(), Color::WHITE, Color::rgb(1., 0.35, 0.3)),
+    };
+
+    for ray in 0..weapon.definition.kind.ray_count() {
+        for segment in 0..=MAX_MIRROR_BOUNCES {
+            commands
+                .spawn_bundle(LaserBundle {
+                    sprite_bundle: SpriteBundle {
+                        sprite: Sprite { color, ..default() },
+                        texture: texture.clone(),
+                        visibility: Visibility { is_visible: false },
+                        ..default()
+                    },
+                    laser: Laser { weapon: entity, ray, segment },
+                })
+                .insert(LineLight2d {
+                    color: light_color,
+                    ..default()
+                })
+                .insert(Name::new("Laser"));
+        }
+
+        commands
+            .spawn_bundle(LaserEndBundle {
+                sprite_bundle: SpriteBundle {
+                    texture: textures.laser_end_texture.clone(),
+                    visibility: Visibility { is_visible: false },
+                    ..default()
+                },
+                laser: Laser { weapon: entity, ray, segment: 0 },
+                laser_end: LaserEnd,
+            })
+            .insert(Name::new("LaserEnd"));
+    }
+}
+
+fn setup_laser_sound(mut commands: Commands, audio: Res<Audio>, sounds: Res<AudioAssets>) {
     commands.insert_resource(LaserSound(audio.play(sounds.laser.clone()).with_volume(0.).with_playback_rate(0.).looped().handle()));
 }
 
@@ -115,100 +275,296 @@ fn drop_laser(mut commands: Commands, laser: Query<Entity, With<Laser>>) {
     }
 }
 
+/// Removes the lasers of players that died in co-op
+fn drop_orphaned_lasers(mut commands: Commands, lasers: Query<(Entity, &Laser)>, weapons: Query<(), With<Weapon>>) {
+    for (entity, laser) in lasers.iter() {
+        if weapons.get(laser.weapon).is_err() {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+fn switch_weapons(
+    mut commands: Commands,
+    mut players: Query<
+        (
+            &mut PlayerActions,
+            &Children,
+            Option<&LaserColor>,
+            &LockedWeapons,
+        ),
+        With<Player>,
+    >,
+    mut weapons: Query<&mut Weapon>,
+    lasers: Query<(Entity, &Laser)>,
+    definitions: Res<WeaponDefinitions>,
+    textures: Res<TextureAssets>,
+) {
+    for (mut actions, children, laser_color, locked_weapons) in players.iter_mut() {
+        let available = |slot: usize| !locked_weapons.0.contains(&definitions[slot].name);
+
+        let switch = match actions.switch_weapon.take() {
+            Some(switch) => switch,
+            None => continue,
+        };
+
+        for child in children.iter() {
+            let mut weapon = match weapons.get_mut(*child) {
+                Ok(weapon) => weapon,
+                Err(_) => continue,
+            };
+
+            let count = definitions.len();
+
+            // Weapons that weren't unlocked yet on the upgrade screen are skipped
+            let slot = match switch {
+                WeaponSwitch::Slot(slot) if slot < count && available(slot) => slot,
+                WeaponSwitch::Slot(_) => continue,
+                WeaponSwitch::Next => match (1..count)
+                    .map(|step| (weapon.slot + step) % count)
+                    .find(|slot| available(*slot))
+                {
+                    Some(slot) => slot,
+                    None => continue,
+                },
+                WeaponSwitch::Previous => match (1..count)
+                    .map(|step| (weapon.slot + count - step) % count)
+                    .find(|slot| available(*slot))
+                {
+                    Some(slot) => slot,
+                    None => continue,
+                },
+            };
+
+            if slot == weapon.slot {
+                continue;
+            }
+
+            // Switching starts charging from the beginning
+            *weapon = Weapon::new(slot, &definitions);
+
+            // The new weapon might fire a different number of rays
+            for (entity, laser) in lasers.iter() {
+                if laser.weapon == *child {
+                    commands.entity(entity).despawn_recursive();
+                }
+            }
+
+            spawn_weapon_lasers(&mut commands, &textures, *child, &weapon, laser_color);
+        }
+    }
+}
+
 fn shoot<'a>(
-    mut weapon: Query<&mut Weapon>,
+    mut weapon: Query<(Entity, &mut Weapon, &Parent)>,
     player_query: Query<&Transform, With<Player>>,
     mut laser_query: Query<
-        (&mut Transform, &mut Visibility, &Handle<Image>),
-        (With<Laser>, Without<LaserEnd>, Without<Player>),
+        (&Laser, &mut Transform, &mut Visibility, &Handle<Image>, &mut LineLight2d),
+        (Without<LaserEnd>, Without<Player>),
     >,
     mut laser_end_query: Query<
-        (&mut Transform, &mut Visibility, &Handle<Image>),
-        (With<Laser>, With<LaserEnd>, Without<Player>),
+        (&Laser, &mut Transform, &mut Visibility, &Handle<Image>),
+        (With<LaserEnd>, Without<Player>),
     >,
     transforms: Query<(Entity, &Transform), (Without<Weapon>, Without<Player>, Without<Laser>)>,
+    global_transforms: Query<&GlobalTransform>,
+    mirrors: Query<&Mirror>,
     mut entity_hit_event_w: EventWriter<EntityHitEvent>,
     time: Res<Time>,
     rapier_context: Res<RapierContext>,
     images: Res<Assets<Image>>,
 ) {
-    let ray_cast_filter = QueryFilter::default();
-    let player_transform = player_query.single();
-    let (mut laser_transform, mut laser_visibility, laser_texture) = laser_query.single_mut();
-    let (mut laser_end_transform, mut laser_end_visibility, laser_end_texture) =
-        laser_end_query.single_mut();
-    let shoot_direction = player_transform.up();
-
-    for mut weapon in weapon.iter_mut() {
-        laser_visibility.is_visible = weapon.status == WeaponStatus::Beaming;
-        laser_end_visibility.is_visible = weapon.status == WeaponStatus::Beaming;
-
-        let laser_texture_height = images
-            .get(laser_texture)
-            .unwrap()
-            .texture_descriptor
-            .size
-            .height as f32;
-
-        let laser_start = player_transform.translation + shoot_direction * 20.;
-
-        match weapon.status {
-            WeaponStatus::Beaming => {
-                if let Some((hit, toi)) = rapier_context.cast_ray(
-                    player_transform.translation.truncate(),
-                    shoot_direction.truncate(),
-                    100000.,
-                    true,
-                    ray_cast_filter,
-                ) {
-                    entity_hit_event_w.send(EntityHitEvent(hit));
-                    info!("entity hit event sent {:?}", hit);
+    for (weapon_entity, mut weapon, parent) in weapon.iter_mut() {
+        let player_transform = match player_query.get(parent.get()) {
+            Ok(player_transform) => player_transform,
+            Err(_) => continue,
+        };
+
+        let ray_angles = weapon.definition.kind.ray_angles(weapon.beaming_time.percent());
+
+        for (ray, ray_angle) in ray_angles.into_iter().enumerate() {
+            let (mut laser_end_transform, mut laser_end_visibility, laser_end_texture) =
+                match laser_end_query.iter_mut().find(|(laser, ..)| laser.weapon == weapon_entity && laser.ray == ray) {
+                    Some((_, transform, visibility, texture)) => (transform, visibility, texture),
+                    // The lasers are spawned a frame after the weapon
+                    None => continue,
+                };
+
+            let beaming = weapon.status == WeaponStatus::Beaming;
+
+            laser_end_visibility.is_visible = beaming;
+
+            if !beaming {
+                for (laser, _, mut laser_visibility, ..) in laser_query.iter_mut() {
+                    if laser.weapon == weapon_entity && laser.ray == ray {
+                        laser_visibility.is_visible = false;
+                    }
+                }
 
-                    let laser_end = player_transform.translation + shoot_direction * (toi + 2.);
+                continue;
+            }
 
-                    let laser_position = (laser_start + laser_end) / 2.;
+            let shoot_direction = Quat::from_rotation_z(ray_angle) * player_transform.up();
+            let beam_width = weapon.definition.beam_width;
+
+            let source = DamageSource {
+                kind: weapon.definition.damage_kind,
+                entity: weapon_entity,
+            };
+
+            let mut origin = player_transform.translation.truncate();
+            let mut direction = shoot_direction.truncate();
+            let mut range = weapon.definition.range;
+            let mut filter = QueryFilter::default();
+
+            // Start, end and direction of each segment of the beam, every mirror it bounces off starts a new one
+            let mut segments = Vec::new();
+            let mut last_hit = None;
+
+            for bounce in 0..=MAX_MIRROR_BOUNCES {
+                let mut hits = beam_hits(
+                    &rapier_context,
+                    &global_transforms,
+                    &weapon,
+                    origin,
+                    direction,
+                    range,
+                    filter,
+                );
+
+                // Nothing behind a mirror gets hit
+                let mirror = hits
+                    .iter()
+                    .position(|(entity, ..)| mirrors.get(*entity).map_or(false, Mirror::reflects));
+
+                if let Some(index) = mirror {
+                    hits.truncate(index + 1);
+                }
 
-                    laser_transform.translation = laser_position;
-                    laser_transform.translation.z = 3.;
+                for (hit, toi, multiplier) in hits.iter() {
+                    if mirrors.get(*hit).is_ok() {
+                        continue;
+                    }
 
-                    laser_transform.rotation = Quat::from_rotation_z(
-                        Vec2::Y.angle_between(shoot_direction.truncate()) - PI / 2.,
-                    );
+                    entity_hit_event_w.send(EntityHitEvent {
+                        entity: *hit,
+                        // The damage adds up for as long as the entity stays in the laser
+                        amount: weapon.definition.damage_per_second * multiplier * time.delta_seconds(),
+                        source,
+                        point: origin + direction * *toi,
+                    });
+                }
 
-                    laser_transform.scale.x =
-                        (laser_end - laser_start).length() / laser_texture_height;
+                // Short range rays end in the air
+                let toi = hits.last().map(|(_, toi, _)| *toi).unwrap_or(range);
+                last_hit = hits.last().map(|(hit, ..)| *hit);
+
+                // The beam comes out a bit in front of the player
+                let start = if bounce == 0 { origin + direction * 20. } else { origin };
+                segments.push((start, origin + direction * toi, direction));
+
+                let mirror = match mirror {
+                    Some(index) => hits[index].0,
+                    None => break,
+                };
+
+                let normal = match transforms.get(mirror) {
+                    Ok((_, mirror_transform)) => mirror_transform.up().truncate(),
+                    Err(_) => break,
+                };
+
+                origin += direction * toi;
+                direction -= 2. * direction.dot(normal) * normal;
+                range -= toi;
+                // Leave the mirror instead of hitting it again right away
+                filter = QueryFilter::default().exclude_collider(mirror);
+            }
 
-                    let mut hit_transform = &Transform::default();
+            for (laser, mut laser_transform, mut laser_visibility, laser_texture, mut laser_light) in laser_query.iter_mut() {
+                if laser.weapon != weapon_entity || laser.ray != ray {
+                    continue;
+                }
 
-                    for (entity, transform) in transforms.iter() {
-                        if hit == entity {
-                            hit_transform = transform;
-                        }
+                let (laser_start, laser_end, segment_direction) = match segments.get(laser.segment) {
+                    Some(segment) => *segment,
+                    None => {
+                        laser_visibility.is_visible = false;
+                        continue;
                     }
+                };
+
+                laser_visibility.is_visible = true;
+
+                let laser_end = laser_end + segment_direction * 2.;
+
+                let laser_texture_height = images
+                    .get(laser_texture)
+                    .unwrap()
+                    .texture_descriptor
+                    .size
+                    .height as f32;
+
+                laser_transform.translation = ((laser_start + laser_end) / 2.).extend(3.);
+
+                laser_transform.rotation = Quat::from_rotation_z(
+                    Vec2::Y.angle_between(segment_direction) - PI / 2.,
+                );
+
+                laser_transform.scale.x =
+                    (laser_end - laser_start).length() / laser_texture_height;
+                laser_transform.scale.y = beam_width / laser_texture_height;
 
-                    laser_end_transform.rotation = Quat::from_rotation_z(
-                        hit_transform.rotation.to_euler(EulerRot::XYZ).2 + PI / 2.,
-                    );
-
-                    let laser_end_texture_size = images
-                        .get(laser_end_texture)
-                        .unwrap()
-                        .texture_descriptor
-                        .size;
-
-                    laser_end_transform.scale = Vec3::new(
-                        LASER_END_WIDTH / laser_end_texture_size.width as f32,
-                        LASER_END_HEIGHT / laser_end_texture_size.height as f32,
-                        1.,
-                    );
-
-                    laser_end_transform.translation = laser_end
-                        + laser_end_transform.right()
-                            * laser_end_transform.scale.y
-                            * laser_end_texture_size.height as f32
-                            / 4.;
+                laser_light.start = laser_start;
+                laser_light.end = laser_end;
+            }
+
+            let laser_end = match segments.last() {
+                Some((_, end, segment_direction)) => {
+                    (*end + *segment_direction * 2.).extend(player_transform.translation.z)
+                }
+                None => continue,
+            };
+
+            laser_end_visibility.is_visible = last_hit.is_some();
+
+            let hit = match last_hit {
+                Some(hit) => hit,
+                None => continue,
+            };
+
+            let mut hit_transform = &Transform::default();
+
+            for (entity, transform) in transforms.iter() {
+                if hit == entity {
+                    hit_transform = transform;
                 }
+            }
+
+            laser_end_transform.rotation = Quat::from_rotation_z(
+                hit_transform.rotation.to_euler(EulerRot::XYZ).2 + PI / 2.,
+            );
+
+            let laser_end_texture_size = images
+                .get(laser_end_texture)
+                .unwrap()
+                .texture_descriptor
+                .size;
+
+            laser_end_transform.scale = Vec3::new(
+                LASER_END_WIDTH / laser_end_texture_size.width as f32,
+                LASER_END_HEIGHT / laser_end_texture_size.height as f32,
+                1.,
+            );
+
+            laser_end_transform.translation = laser_end
+                + laser_end_transform.right()
+                    * laser_end_transform.scale.y
+                    * laser_end_texture_size.height as f32
+                    / 4.;
+        }
 
+        match weapon.status {
+            WeaponStatus::Beaming => {
                 if weapon.beaming_time.tick(time.delta()).just_finished() {
                     weapon.status = WeaponStatus::Idle;
                     weapon.cooldown.reset();
@@ -224,8 +580,77 @@ fn shoot<'a>(
     }
 }
 
+/// Everything a beam going from `origin` hits with the damage multiplier and the distance to it,
+/// the last one is where it ends
+fn beam_hits(
+    rapier_context: &RapierContext,
+    global_transforms: &Query<&GlobalTransform>,
+    weapon: &Weapon,
+    origin: Vec2,
+    direction: Vec2,
+    range: f32,
+    filter: QueryFilter,
+) -> Vec<(Entity, f32, f32)> {
+    let beam_width = weapon.definition.beam_width;
+    let mut hits = Vec::new();
+
+    if weapon.pierces {
+        // Whatever the middle of the beam goes through
+        let mut centered = Vec::new();
+
+        rapier_context.intersections_with_ray(origin, direction, range, true, filter, |entity, _| {
+            centered.push(entity);
+            true
+        });
+
+        // The whole length of the beam at once
+        let beam_shape = Collider::cuboid(range / 2., beam_width / 2.);
+
+        rapier_context.intersections_with_shape(
+            origin + direction * range / 2.,
+            Vec2::X.angle_between(direction),
+            &beam_shape,
+            filter,
+            |entity| {
+                let toi = global_transforms
+                    .get(entity)
+                    .map(|transform| (transform.translation().truncate() - origin).dot(direction).max(0.))
+                    .unwrap_or(0.);
+
+                let multiplier = if centered.contains(&entity) {
+                    1.
+                } else {
+                    BEAM_GRAZE_MULTIPLIER
+                };
+
+                hits.push((entity, toi, multiplier));
+                true
+            },
+        );
+
+        hits.sort_by(|(_, a, _), (_, b, _)| a.total_cmp(b));
+    } else if let Some((entity, toi)) =
+        rapier_context.cast_shape(origin, 0., direction, &Collider::ball(beam_width / 2.), range, filter)
+    {
+        // Only the edge grazes it unless the middle of the beam hits it too
+        let centered = rapier_context
+            .cast_ray(origin, direction, range, true, filter)
+            .map_or(false, |(ray_entity, _)| ray_entity == entity);
+
+        let multiplier = if centered { 1. } else { BEAM_GRAZE_MULTIPLIER };
+
+        hits.push((entity, toi.toi + beam_width / 2., multiplier));
+    }
+
+    hits
+}
+
 fn play_laser_sound(weapon: Query<&Weapon>, laser_sound: Res<LaserSound>, mut audio_instances: ResMut<Assets<AudioInstance>>, time: Res<Time>) {
-    let weapon = weapon.single();
+    // With more players the sound follows the weapon closest to firing
+    let weapon = match weapon.iter().max_by(|a, b| sound_progress(a).total_cmp(&sound_progress(b))) {
+        Some(weapon) => weapon,
+        None => return,
+    };
 
     if let Some(laser_audio) = audio_instances.get_mut(&laser_sound.0) {
         let audio_tween = AudioTween::linear(time.delta());
@@ -243,4 +668,12 @@ fn play_laser_sound(weapon: Query<&Weapon>, laser_sound: Res<LaserSound>, mut au
             }
         }
     }
-}
\ No newline at end of file
+}
+
+/// 0 right after beaming, 1 when ready and 2 while beaming
+fn sound_progress(weapon: &Weapon) -> f32 {
+    match weapon.status {
+        WeaponStatus::Idle => 1. - weapon.cooldown.percent_left(),
+        WeaponStatus::Beaming => 2.,
+    }
+}
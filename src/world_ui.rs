@@ -0,0 +1,234 @@
+use bevy::{math::Vec3Swizzles, prelude::*, sprite::Anchor};
+
+use crate::{
+    character::Health,
+    enemy::{Enemy, EnemyDamagedEvent},
+    loading::FontAssets,
+    score::ScoreEvent,
+    GameState,
+};
+
+pub struct WorldUiPlugin;
+
+/// This plugin draws the UI living in the game world instead of the overlay,
+/// like the health bars above the enemies and the numbers popping out of them
+impl Plugin for WorldUiPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::on_update(GameState::Playing)
+                .with_system(spawn_enemy_health_bars)
+                .with_system(update_enemy_health_bars)
+                .with_system(spawn_damage_numbers)
+                .with_system(spawn_score_numbers)
+                .with_system(animate_floating_text),
+        )
+        .add_system_set(SystemSet::on_exit(GameState::Playing).with_system(drop_world_ui));
+    }
+}
+
+pub const ENEMY_HEALTH_BAR_WIDTH: f32 = 40.;
+pub const ENEMY_HEALTH_BAR_HEIGHT: f32 = 5.;
+pub const ENEMY_HEALTH_BAR_OFFSET: f32 = 36.;
+/// How fast the health bars fade in and out
+pub const ENEMY_HEALTH_BAR_FADE_SPEED: f32 = 4.;
+
+pub const FLOATING_TEXT_LIFETIME: f32 = 0.8;
+pub const FLOATING_TEXT_SPEED: f32 = 40.;
+
+/// Marks everything spawned by this plugin
+#[derive(Component)]
+struct WorldUi;
+
+/// The background of the health bar of the given enemy
+#[derive(Component)]
+struct EnemyHealthBar(Entity);
+
+#[derive(Component)]
+struct EnemyHealthBarFill;
+
+#[derive(Component, Deref, DerefMut)]
+struct FloatingText(Timer);
+
+fn spawn_enemy_health_bars(mut commands: Commands, enemies: Query<Entity, Added<Enemy>>) {
+    for enemy in enemies.iter() {
+        commands
+            .spawn_bundle(SpriteBundle {
+                sprite: Sprite {
+                    color: Color::rgba(0., 0., 0., 0.),
+                    custom_size: Some(Vec2::new(
+                        ENEMY_HEALTH_BAR_WIDTH + 2.,
+                        ENEMY_HEALTH_BAR_HEIGHT + 2.,
+                    )),
+                    ..default()
+                },
+                ..default()
+            })
+            .with_children(|parent| {
+                parent
+                    .spawn_bundle(SpriteBundle {
+                        sprite: Sprite {
+                            color: Color::rgba(0.9, 0.1, 0.1, 0.),
+                            custom_size: Some(Vec2::new(
+                                ENEMY_HEALTH_BAR_WIDTH,
+                                ENEMY_HEALTH_BAR_HEIGHT,
+                            )),
+                            anchor: Anchor::CenterLeft,
+                            ..default()
+                        },
+                        transform: Transform::from_xyz(-ENEMY_HEALTH_BAR_WIDTH / 2., 0., 0.1),
+                        ..default()
+                    })
+                    .insert(EnemyHealthBarFill);
+            })
+            .insert(Name::new("EnemyHealthBar"))
+            .insert(EnemyHealthBar(enemy))
+            .insert(WorldUi);
+    }
+}
+
+fn update_enemy_health_bars(
+    mut commands: Commands,
+    mut health_bars: Query<
+        (
+            Entity,
+            &EnemyHealthBar,
+            &mut Transform,
+            &mut Sprite,
+            &Children,
+        ),
+        Without<EnemyHealthBarFill>,
+    >,
+    mut fills: Query<&mut Sprite, With<EnemyHealthBarFill>>,
+    enemies: Query<(&Transform, &Health), (With<Enemy>, Without<EnemyHealthBar>)>,
+    time: Res<Time>,
+) {
+    for (entity, health_bar, mut transform, mut background, children) in health_bars.iter_mut() {
+        let (enemy_transform, health) = match enemies.get(health_bar.0) {
+            Ok(enemy) => enemy,
+            Err(_) => {
+                commands.entity(entity).despawn_recursive();
+                continue;
+            }
+        };
+
+        transform.translation =
+            (enemy_transform.translation.xy() + Vec2::Y * ENEMY_HEALTH_BAR_OFFSET).extend(8.);
+
+        let percent = (health.get_health() / health.get_max_health()).clamp(0., 1.);
+
+        // Only show the health bar once the enemy got hurt
+        let target_alpha = if percent < 1. { 1. } else { 0. };
+        let alpha = background.color.a();
+        let alpha = alpha
+            + (target_alpha - alpha) * (ENEMY_HEALTH_BAR_FADE_SPEED * time.delta_seconds()).min(1.);
+
+        background.color.set_a(alpha);
+
+        for child in children.iter() {
+            if let Ok(mut fill) = fills.get_mut(*child) {
+                fill.color.set_a(alpha);
+                fill.custom_size = Some(Vec2::new(
+                    ENEMY_HEALTH_BAR_WIDTH * percent,
+                    ENEMY_HEALTH_BAR_HEIGHT,
+                ));
+            }
+        }
+    }
+}
+
+fn spawn_damage_numbers(
+    mut commands: Commands,
+    mut enemy_damaged_events: EventReader<EnemyDamagedEvent>,
+    fonts: Res<FontAssets>,
+) {
+    for event in enemy_damaged_events.iter() {
+        let damage_color = if event.critical {
+            Color::ORANGE
+        } else {
+            Color::WHITE
+        };
+
+        spawn_floating_text(
+            &mut commands,
+            &fonts,
+            format!("{}", event.amount.round()),
+            damage_color,
+            event.position,
+        );
+    }
+}
+
+fn spawn_score_numbers(
+    mut commands: Commands,
+    mut score_events: EventReader<ScoreEvent>,
+    fonts: Res<FontAssets>,
+) {
+    for event in score_events.iter() {
+        spawn_floating_text(
+            &mut commands,
+            &fonts,
+            format!("+{}", event.points),
+            Color::GOLD,
+            event.position + Vec2::Y * 20.,
+        );
+    }
+}
+
+fn spawn_floating_text(
+    commands: &mut Commands,
+    fonts: &FontAssets,
+    value: String,
+    color: Color,
+    position: Vec2,
+) {
+    // Scatter the numbers a bit so that they don't overlap
+    let offset = Vec2::new(rand::random::<f32>() - 0.5, rand::random::<f32>() - 0.5) * 16.;
+
+    commands
+        .spawn_bundle(Text2dBundle {
+            text: Text::from_section(
+                value,
+                TextStyle {
+                    font: fonts.fira_sans.clone(),
+                    font_size: 18.,
+                    color,
+                },
+            )
+            .with_alignment(TextAlignment::CENTER),
+            transform: Transform::from_translation((position + offset).extend(9.)),
+            ..default()
+        })
+        .insert(Name::new("FloatingText"))
+        .insert(FloatingText(Timer::from_seconds(
+            FLOATING_TEXT_LIFETIME,
+            false,
+        )))
+        .insert(WorldUi);
+}
+
+fn animate_floating_text(
+    mut commands: Commands,
+    mut floating_texts: Query<(Entity, &mut FloatingText, &mut Transform, &mut Text)>,
+    time: Res<Time>,
+) {
+    for (entity, mut floating_text, mut transform, mut text) in floating_texts.iter_mut() {
+        if floating_text.tick(time.delta()).finished() {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+
+        transform.translation.y += FLOATING_TEXT_SPEED * time.delta_seconds();
+
+        let alpha = floating_text.percent_left();
+
+        for section in text.sections.iter_mut() {
+            section.style.color.set_a(alpha);
+        }
+    }
+}
+
+fn drop_world_ui(mut commands: Commands, world_ui: Query<Entity, With<WorldUi>>) {
+    for entity in world_ui.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}